@@ -1,12 +1,108 @@
+mod backup;
+mod backup_folder;
+mod baseline;
+mod dependency;
+mod diff;
+mod encryption;
+mod expand;
+mod file_store;
+mod formats;
+mod history;
+mod import;
+mod journal;
+mod linux_store;
+mod path_analysis;
+mod path_cleanup;
+mod path_edit;
+mod profiles;
+#[cfg(windows)]
+mod registry;
+mod secrets;
+mod stale;
+mod store;
+mod toolchains;
+mod transaction;
+mod validation;
+mod which;
+
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use tauri::command;
-use winreg::enums::*;
-use winreg::RegKey;
+use transaction::EnvChange;
+
+use backup::BackupFile;
+pub use backup::ChecksumStatus;
+pub use backup_folder::{
+    BackupAnnotation, BackupPrunePolicy, BackupPruneResult, BackupValidationReport,
+};
+pub use baseline::{BaselineFixResult, BaselineReport};
+pub use dependency::{DependencyGraph, ImpactReport};
+pub use diff::{DiffSource, EnvDiffReport};
+pub use expand::{Expander, ExpansionReport};
+pub use file_store::FileStore;
+pub use formats::{ExportFormat, ExportSelection};
+pub use history::SnapshotPolicy;
+pub use import::{ImportOptions, ImportPreview, ImportReport};
+pub use journal::{ChangeOrigin, JournalEntry, UndoResult};
+pub use linux_store::{LinuxEnvPaths, LinuxStore};
+pub use path_analysis::PathAnalysisReport;
+pub use path_cleanup::{PathCleanupOptions, PathCleanupResult};
+pub use path_edit::{PathEdit, PathEditResult};
+pub use profiles::{ActiveProfileStatus, EnvProfile};
+pub use secrets::{SecretHandling, SecretRef, SecretVar};
+pub use stale::StaleScanReport;
+pub use store::{
+    current_store, replace_store, EnvFlavor, EnvScope, EnvSnapshot, EnvStore, EnvStoreInfo,
+};
+pub use toolchains::{DiscoveryOptions, DiscoveryReport, ToolLocation};
+pub use validation::{HealthReport, RuleSettings, ValidationRule};
+pub use which::WhichReport;
+
+/// 变量值类型，对应注册表中的 REG_SZ / REG_EXPAND_SZ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvValueKind {
+    String,
+    ExpandString,
+}
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl EnvValueKind {
+    /// 根据值推断类型：包含 `%NAME%` 引用时视为可展开字符串
+    pub fn infer(value: &str) -> Self {
+        if expand::references(value, EnvFlavor::Windows).is_empty() {
+            EnvValueKind::String
+        } else {
+            EnvValueKind::ExpandString
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(from = "RawEnvVar")]
 pub struct EnvVar {
     pub name: String,
     pub value: String,
+    pub kind: EnvValueKind,
+}
+
+// 旧版备份文件没有 kind 字段，读取时根据值推断
+#[derive(Deserialize)]
+struct RawEnvVar {
+    name: String,
+    value: String,
+    #[serde(default)]
+    kind: Option<EnvValueKind>,
+}
+
+impl From<RawEnvVar> for EnvVar {
+    fn from(raw: RawEnvVar) -> Self {
+        let kind = raw.kind.unwrap_or_else(|| EnvValueKind::infer(&raw.value));
+        EnvVar {
+            name: raw.name,
+            value: raw.value,
+            kind,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -15,64 +111,21 @@ pub struct EnvVarsResponse {
     pub user_vars: Vec<EnvVar>,
 }
 
+/// 修改单个变量或停用配置方案的结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnvWriteResult {
+    /// 修改已生效但出现的问题（如修改日志写入失败）
+    pub warning: Option<String>,
+}
+
 // 读取系统环境变量
 fn read_system_env_vars() -> Result<Vec<EnvVar>, String> {
-    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
-    let env_key = hklm
-        .open_subkey("SYSTEM\\CurrentControlSet\\Control\\Session Manager\\Environment")
-        .map_err(|e| format!("无法打开系统环境变量注册表: {}", e))?;
-
-    let mut vars = Vec::new();
-
-    for (name, value) in env_key.enum_values().map(|x| x.unwrap()) {
-        let value_str = match value {
-            winreg::RegValue { vtype: REG_SZ, .. }
-            | winreg::RegValue {
-                vtype: REG_EXPAND_SZ,
-                ..
-            } => env_key.get_value::<String, _>(&name).unwrap_or_default(),
-            _ => continue, // 跳过非字符串类型的值
-        };
-
-        vars.push(EnvVar {
-            name,
-            value: value_str,
-        });
-    }
-
-    // 按名称排序
-    vars.sort_by(|a, b| a.name.cmp(&b.name));
-    Ok(vars)
+    current_store().list(EnvScope::System)
 }
 
 // 读取用户环境变量
 fn read_user_env_vars() -> Result<Vec<EnvVar>, String> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let env_key = hkcu
-        .open_subkey("Environment")
-        .map_err(|e| format!("无法打开用户环境变量注册表: {}", e))?;
-
-    let mut vars = Vec::new();
-
-    for (name, value) in env_key.enum_values().map(|x| x.unwrap()) {
-        let value_str = match value {
-            winreg::RegValue { vtype: REG_SZ, .. }
-            | winreg::RegValue {
-                vtype: REG_EXPAND_SZ,
-                ..
-            } => env_key.get_value::<String, _>(&name).unwrap_or_default(),
-            _ => continue, // 跳过非字符串类型的值
-        };
-
-        vars.push(EnvVar {
-            name,
-            value: value_str,
-        });
-    }
-
-    // 按名称排序
-    vars.sort_by(|a, b| a.name.cmp(&b.name));
-    Ok(vars)
+    current_store().list(EnvScope::User)
 }
 
 #[command]
@@ -86,156 +139,89 @@ pub fn get_env_vars() -> Result<EnvVarsResponse, String> {
     })
 }
 
+// 未指定 kind 时沿用已有变量的类型，新变量根据值推断
 #[command]
-pub fn set_env_var(name: String, value: String, is_system: bool) -> Result<(), String> {
-    if is_system {
-        // 设置系统环境变量（需要管理员权限）
-        let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
-        let env_key = hklm
-            .open_subkey_with_flags(
-                "SYSTEM\\CurrentControlSet\\Control\\Session Manager\\Environment",
-                KEY_WRITE,
-            )
-            .map_err(|e| {
-                format!(
-                    "无法打开系统环境变量注册表进行写入（可能需要管理员权限）: {}",
-                    e
-                )
-            })?;
-
-        env_key
-            .set_value(&name, &value)
-            .map_err(|e| format!("无法设置系统环境变量: {}", e))?;
-    } else {
-        // 设置用户环境变量
-        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-        let env_key = hkcu
-            .open_subkey_with_flags("Environment", KEY_WRITE)
-            .map_err(|e| format!("无法打开用户环境变量注册表进行写入: {}", e))?;
-
-        env_key
-            .set_value(&name, &value)
-            .map_err(|e| format!("无法设置用户环境变量: {}", e))?;
-    }
+pub fn set_env_var(
+    name: String,
+    value: String,
+    is_system: bool,
+    kind: Option<EnvValueKind>,
+) -> Result<EnvWriteResult, String> {
+    let scope = EnvScope::from_is_system(is_system);
+    let store = current_store();
+    let kind = match kind {
+        Some(kind) => kind,
+        None => store
+            .get(scope, &name)?
+            .map(|existing| existing.kind)
+            .unwrap_or_else(|| EnvValueKind::infer(&value)),
+    };
 
-    // 通知系统环境变量已更改
-    unsafe {
-        use std::ffi::CString;
-
-        extern "system" {
-            fn SendMessageTimeoutA(
-                hwnd: *mut std::ffi::c_void,
-                msg: u32,
-                wparam: usize,
-                lparam: isize,
-                fuflags: u32,
-                utimeout: u32,
-                lpdwresult: *mut usize,
-            ) -> isize;
-        }
+    let snapshot = EnvSnapshot::load(&*store)?;
 
-        const HWND_BROADCAST: *mut std::ffi::c_void = 0xffff as *mut std::ffi::c_void;
-        const WM_SETTINGCHANGE: u32 = 0x001A;
-        const SMTO_ABORTIFHUNG: u32 = 0x0002;
-
-        let env_cstring = CString::new("Environment").unwrap();
-        let mut result: usize = 0;
-
-        SendMessageTimeoutA(
-            HWND_BROADCAST,
-            WM_SETTINGCHANGE,
-            0,
-            env_cstring.as_ptr() as isize,
-            SMTO_ABORTIFHUNG,
-            5000,
-            &mut result,
-        );
-    }
+    snapshot_before_change()?;
+    let changes = [EnvChange::Set {
+        scope,
+        var: EnvVar { name, value, kind },
+    }];
+    let warning = transaction::apply_changes(&*store, &snapshot, &changes, ChangeOrigin::UiEdit)
+        .map_err(|e| e.message(&changes))?;
 
-    Ok(())
+    // 通知系统环境变量已更改
+    store.notify_changed();
+
+    Ok(EnvWriteResult { warning })
 }
 
 #[command]
-pub fn delete_env_var(name: String, is_system: bool) -> Result<(), String> {
-    if is_system {
-        // 删除系统环境变量（需要管理员权限）
-        let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
-        let env_key = hklm
-            .open_subkey_with_flags(
-                "SYSTEM\\CurrentControlSet\\Control\\Session Manager\\Environment",
-                KEY_WRITE,
-            )
-            .map_err(|e| {
-                format!(
-                    "无法打开系统环境变量注册表进行写入（可能需要管理员权限）: {}",
-                    e
-                )
-            })?;
-
-        env_key
-            .delete_value(&name)
-            .map_err(|e| format!("无法删除系统环境变量: {}", e))?;
-    } else {
-        // 删除用户环境变量
-        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-        let env_key = hkcu
-            .open_subkey_with_flags("Environment", KEY_WRITE)
-            .map_err(|e| format!("无法打开用户环境变量注册表进行写入: {}", e))?;
-
-        env_key
-            .delete_value(&name)
-            .map_err(|e| format!("无法删除用户环境变量: {}", e))?;
-    }
+pub fn delete_env_var(name: String, is_system: bool) -> Result<EnvWriteResult, String> {
+    let store = current_store();
+    let snapshot = EnvSnapshot::load(&*store)?;
+
+    snapshot_before_change()?;
+    let changes = [EnvChange::Delete {
+        scope: EnvScope::from_is_system(is_system),
+        name,
+    }];
+    let warning = transaction::apply_changes(&*store, &snapshot, &changes, ChangeOrigin::UiEdit)
+        .map_err(|e| e.message(&changes))?;
 
     // 通知系统环境变量已更改
-    unsafe {
-        use std::ffi::CString;
-
-        extern "system" {
-            fn SendMessageTimeoutA(
-                hwnd: *mut std::ffi::c_void,
-                msg: u32,
-                wparam: usize,
-                lparam: isize,
-                fuflags: u32,
-                utimeout: u32,
-                lpdwresult: *mut usize,
-            ) -> isize;
-        }
+    store.notify_changed();
 
-        const HWND_BROADCAST: *mut std::ffi::c_void = 0xffff as *mut std::ffi::c_void;
-        const WM_SETTINGCHANGE: u32 = 0x001A;
-        const SMTO_ABORTIFHUNG: u32 = 0x0002;
-
-        let env_cstring = CString::new("Environment").unwrap();
-        let mut result: usize = 0;
-
-        SendMessageTimeoutA(
-            HWND_BROADCAST,
-            WM_SETTINGCHANGE,
-            0,
-            env_cstring.as_ptr() as isize,
-            SMTO_ABORTIFHUNG,
-            5000,
-            &mut result,
-        );
-    }
-
-    Ok(())
+    Ok(EnvWriteResult { warning })
 }
 
 #[command]
 pub fn check_admin_privileges() -> Result<bool, String> {
-    // 尝试以写权限打开系统环境变量注册表键来检测管理员权限
-    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+    // 能写入系统作用域即视为拥有管理员权限
+    Ok(current_store().can_write(EnvScope::System))
+}
 
-    match hklm.open_subkey_with_flags(
-        "SYSTEM\\CurrentControlSet\\Control\\Session Manager\\Environment",
-        KEY_WRITE,
-    ) {
-        Ok(_) => Ok(true),   // 可以打开，说明有管理员权限
-        Err(_) => Ok(false), // 无法打开，说明没有管理员权限
+// 获取当前使用的环境变量后端
+#[command]
+pub fn get_env_store_info() -> Result<EnvStoreInfo, String> {
+    Ok(current_store().info())
+}
+
+// 使用 Linux 后端，可覆盖 environment.d、~/.profile 和 /etc/environment 的路径
+#[command]
+pub fn set_env_store_linux(paths: Option<LinuxEnvPaths>) -> Result<EnvStoreInfo, String> {
+    let store = LinuxStore::new(paths.unwrap_or_default());
+    replace_store(Some(Arc::new(store)));
+
+    Ok(current_store().info())
+}
+
+// 将环境变量命令指向文件后端，传入空值时恢复默认后端
+#[command]
+pub fn set_env_store_file(file_path: Option<String>) -> Result<EnvStoreInfo, String> {
+    match file_path.filter(|path| !path.trim().is_empty()) {
+        Some(path) => replace_store(Some(Arc::new(FileStore::new(path)))),
+        None => replace_store(None),
     }
+
+    Ok(current_store().info())
 }
 
 // 请求管理员权限重启应用 - 使用 Tauri process 插件优雅重启
@@ -278,18 +264,38 @@ pub async fn request_admin_privileges(app_handle: tauri::AppHandle) -> Result<()
     Ok(())
 }
 
-// 导出配置结构
-#[derive(Debug, Serialize, Deserialize)]
+// 导出配置结构，读写和版本迁移见 backup 模块
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnvVarExport {
     pub export_info: ExportInfo,
     pub system_vars: Vec<EnvVar>,
     pub user_vars: Vec<EnvVar>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportInfo {
+    /// 备份文件的结构版本，旧版本的文件读取时会迁移到当前版本
+    pub schema_version: u32,
     pub export_time: String,
-    pub version: String,
+    /// 导出时的应用版本
+    #[serde(default)]
+    pub app_version: Option<String>,
+    /// 导出所在的计算机、用户和操作系统，旧版本的备份和 dotenv / .reg 文件没有这些信息
+    #[serde(default)]
+    pub hostname: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub os: Option<String>,
+    /// 敏感变量的处理方式，旧版本导出的文件没有该字段，视为原样保留
+    #[serde(default)]
+    pub secrets: SecretHandling,
+    /// 被隐藏或省略的变量
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secret_vars: Vec<SecretRef>,
+    /// 除校验和外全部内容的 SHA-256，导入和扫描时校验
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
 }
 
 // 获取用户文档文件夹路径
@@ -299,16 +305,16 @@ pub async fn get_documents_dir() -> Result<String, String> {
     Ok(documents_dir.to_string_lossy().to_string())
 }
 
-// 导出环境变量到 JSON 文件，支持自定义路径
+// 导出环境变量到 JSON 文件，支持自定义路径；提供密码时写入加密备份
 #[command]
-pub async fn export_env_vars_to_path(file_path: String) -> Result<String, String> {
+pub async fn export_env_vars_to_path(
+    file_path: String,
+    secrets: Option<SecretHandling>,
+    passphrase: Option<String>,
+) -> Result<String, String> {
     use chrono::Utc;
     use std::fs;
 
-    // 获取当前环境变量
-    let system_vars = read_system_env_vars()?;
-    let user_vars = read_user_env_vars()?;
-
     // 如果未提供文件路径，使用默认文档路径
     let final_path = if file_path.is_empty() {
         let documents_dir =
@@ -325,19 +331,27 @@ pub async fn export_env_vars_to_path(file_path: String) -> Result<String, String
         fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
     }
 
-    // 创建导出数据
-    let export_data = EnvVarExport {
-        export_info: ExportInfo {
-            export_time: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-            version: "1.0".to_string(),
-        },
-        system_vars,
-        user_vars,
+    // 创建导出数据，未加密时敏感变量默认隐藏值（导入时会保留这些变量的当前值）
+    let mut export_data = current_export()?;
+    let default_secrets = if passphrase.is_some() {
+        SecretHandling::Include
+    } else {
+        SecretHandling::Redact
     };
+    secrets::mask_export(
+        current_store().flavor(),
+        secrets.unwrap_or(default_secrets),
+        &mut export_data,
+    );
 
     // 序列化为 JSON
-    let json_content =
-        serde_json::to_string_pretty(&export_data).map_err(|e| format!("序列化数据失败: {}", e))?;
+    let json_content = match &passphrase {
+        Some(passphrase) => {
+            serde_json::to_string_pretty(&encryption::encrypt(&export_data, passphrase)?)
+                .map_err(|e| format!("序列化数据失败: {}", e))?
+        }
+        None => backup::to_json(&export_data)?,
+    };
 
     // 写入文件
     fs::write(&final_path, json_content).map_err(|e| format!("写入文件失败: {}", e))?;
@@ -345,10 +359,68 @@ pub async fn export_env_vars_to_path(file_path: String) -> Result<String, String
     Ok(final_path.to_string_lossy().to_string())
 }
 
+// 以导出格式读取当前的环境变量
+fn current_export() -> Result<EnvVarExport, String> {
+    Ok(backup::new_export(
+        read_system_env_vars()?,
+        read_user_env_vars()?,
+    ))
+}
+
+// 按指定格式导出环境变量（dotenv、PowerShell、shell 脚本、.reg、CSV），可选择作用域和变量
+#[command]
+pub async fn export_env_vars_as(
+    file_path: String,
+    format: ExportFormat,
+    selection: Option<ExportSelection>,
+    secrets: Option<SecretHandling>,
+) -> Result<String, String> {
+    use chrono::Utc;
+    use std::fs;
+
+    let selection = selection.unwrap_or_default();
+    let store = current_store();
+    let snapshot = EnvSnapshot::load(&*store)?;
+
+    // 这些格式通常会被直接执行或导入，敏感变量默认省略
+    let secrets = secrets.unwrap_or(SecretHandling::Omit);
+    let mut masked = Vec::new();
+    for scope in [EnvScope::System, EnvScope::User] {
+        for var in selection.select(snapshot.flavor, scope, snapshot.vars(scope)) {
+            if let Some(var) = secrets::mask_var(snapshot.flavor, secrets, var) {
+                masked.push((scope, var));
+            }
+        }
+    }
+    let vars: Vec<(EnvScope, &EnvVar)> = masked.iter().map(|(scope, var)| (*scope, var)).collect();
+    if vars.is_empty() {
+        return Err("没有符合条件的环境变量".to_string());
+    }
+
+    let final_path = if file_path.is_empty() {
+        let documents_dir =
+            dirs::document_dir().ok_or_else(|| "无法获取文档文件夹路径".to_string())?;
+        let timestamp = Utc::now().format("%Y%m%d_%H%M%S");
+        let filename = format!("环境变量导出_{}.{}", timestamp, format.extension());
+        documents_dir.join(filename)
+    } else {
+        std::path::PathBuf::from(&file_path)
+    };
+
+    if let Some(parent) = final_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+    }
+
+    fs::write(&final_path, formats::render(format, snapshot.flavor, &vars))
+        .map_err(|e| format!("写入文件失败: {}", e))?;
+
+    Ok(final_path.to_string_lossy().to_string())
+}
+
 // 导出环境变量到 JSON 文件（向后兼容）
 #[command]
 pub async fn export_env_vars() -> Result<String, String> {
-    export_env_vars_to_path(String::new()).await
+    export_env_vars_to_path(String::new(), None, None).await
 }
 
 // 在文件管理器中显示文件
@@ -379,166 +451,553 @@ pub async fn open_folder(folder_path: String) -> Result<(), String> {
     Ok(())
 }
 
-// 导入环境变量配置
+// 导入环境变量配置，options 指定导入模式和作用域，默认合并导入两个作用域；
+// 加密备份需要提供 passphrase
 #[command]
-pub async fn import_env_vars(file_path: String) -> Result<String, String> {
-    use std::fs;
+pub async fn import_env_vars(
+    file_path: String,
+    options: Option<ImportOptions>,
+    passphrase: Option<String>,
+) -> Result<ImportReport, String> {
+    let import_data = backup::read(&file_path, passphrase.as_deref())?;
+
+    let store = current_store();
+    let report = import::run_import(
+        &*store,
+        &import_data,
+        &options.unwrap_or_default(),
+        snapshot_before_change,
+    )?;
+    if report.has_changes() {
+        store.notify_changed();
+    }
 
-    // 读取文件内容
-    let json_content =
-        fs::read_to_string(&file_path).map_err(|e| format!("读取文件失败: {}", e))?;
+    Ok(report)
+}
 
-    // 尝试解析 JSON - 兼容新旧格式
-    let import_data: EnvVarExport =
-        if let Ok(data) = serde_json::from_str::<EnvVarExport>(&json_content) {
-            // 新格式：包含 export_info
-            data
-        } else {
-            // 尝试解析旧格式
-            match serde_json::from_str::<serde_json::Value>(&json_content) {
-                Ok(json_value) => {
-                    // 旧格式1：system/user 字段
-                    if let (Some(system), Some(user)) = (
-                        json_value
-                            .get("system")
-                            .and_then(|v| serde_json::from_value::<Vec<EnvVar>>(v.clone()).ok()),
-                        json_value
-                            .get("user")
-                            .and_then(|v| serde_json::from_value::<Vec<EnvVar>>(v.clone()).ok()),
-                    ) {
-                        EnvVarExport {
-                            export_info: ExportInfo {
-                                export_time: json_value
-                                    .get("exportTime")
-                                    .and_then(|v| v.as_str())
-                                    .unwrap_or("未知")
-                                    .to_string(),
-                                version: json_value
-                                    .get("appVersion")
-                                    .and_then(|v| v.as_str())
-                                    .unwrap_or("1.0")
-                                    .to_string(),
-                            },
-                            system_vars: system,
-                            user_vars: user,
-                        }
-                    } else {
-                        return Err("JSON文件格式不正确，缺少必要的环境变量数据".to_string());
-                    }
-                }
-                Err(e) => return Err(format!("解析 JSON 文件失败: {}", e)),
-            }
+// 预览导入：返回每个变量相对当前环境的变化，不做任何写入
+#[command]
+pub async fn preview_import_env_vars(
+    file_path: String,
+    options: Option<ImportOptions>,
+    passphrase: Option<String>,
+) -> Result<ImportPreview, String> {
+    let import_data = backup::read(&file_path, passphrase.as_deref())?;
+    let snapshot = EnvSnapshot::load(&*current_store())?;
+    Ok(import::preview_import(
+        &snapshot,
+        &import_data,
+        &options.unwrap_or_default(),
+    ))
+}
+
+// 对比两个备份文件，target_path 为空时与当前环境对比；其中的加密备份使用 passphrase 解密
+#[command]
+pub async fn diff_env_backups(
+    base_path: String,
+    target_path: Option<String>,
+    secrets: Option<SecretHandling>,
+    passphrase: Option<String>,
+) -> Result<EnvDiffReport, String> {
+    let load = |file_path: Option<String>| -> Result<(EnvVarExport, DiffSource), String> {
+        let data = match &file_path {
+            Some(file_path) => backup::read(file_path, passphrase.as_deref())?,
+            None => current_export()?,
         };
+        let source = DiffSource {
+            file_path,
+            export_time: data.export_info.export_time.clone(),
+        };
+        Ok((data, source))
+    };
 
-    let mut imported_count = 0;
-    let mut failed_count = 0;
-    let mut failed_vars = Vec::new();
-
-    // 导入系统变量
-    for var in import_data.system_vars {
-        match set_env_var_internal(&var.name, &var.value, true) {
-            Ok(_) => imported_count += 1,
-            Err(e) => {
-                failed_count += 1;
-                failed_vars.push(format!("系统变量 {}: {}", var.name, e));
-            }
-        }
+    let (mut base, base_source) = load(Some(base_path))?;
+    let (mut target, target_source) = load(target_path)?;
+
+    // 备份中被隐藏或省略的变量无法比较，按与另一侧相同处理
+    let flavor = current_store().flavor();
+    secrets::align_masked(flavor, &mut base, &mut target);
+    secrets::align_masked(flavor, &mut target, &mut base);
+
+    Ok(diff::diff_exports(
+        flavor,
+        (&base, base_source),
+        (&target, target_source),
+        secrets.unwrap_or(SecretHandling::Redact),
+    ))
+}
+
+// 修改环境变量前按保留策略自动创建快照
+fn snapshot_before_change() -> Result<(), String> {
+    history::take_snapshot(&current_export()?)
+        .map(|_| ())
+        .map_err(|e| format!("创建修改前快照失败: {}", e))
+}
+
+// 获取修改日志，最新的在前，limit 为空时返回全部
+#[command]
+pub fn list_env_journal(limit: Option<usize>) -> Result<Vec<JournalEntry>, String> {
+    let mut entries = journal::read_entries()?;
+    entries.reverse();
+    if let Some(limit) = limit {
+        entries.truncate(limit);
     }
+    Ok(entries)
+}
 
-    // 导入用户变量
-    for var in import_data.user_vars {
-        match set_env_var_internal(&var.name, &var.value, false) {
-            Ok(_) => imported_count += 1,
-            Err(e) => {
-                failed_count += 1;
-                failed_vars.push(format!("用户变量 {}: {}", var.name, e));
-            }
-        }
+// 撤销单条修改记录
+#[command]
+pub fn undo_env_change(entry_id: u64, force: Option<bool>) -> Result<UndoResult, String> {
+    let entries = journal::read_entries()?;
+    let entry = entries
+        .iter()
+        .find(|entry| entry.id == entry_id)
+        .ok_or_else(|| format!("修改记录不存在: {}", entry_id))?;
+
+    undo_entries(&[entry], force.unwrap_or(false))
+}
+
+// 撤销某个时间点（含）之后的全部修改
+#[command]
+pub fn undo_env_changes_since(since: String, force: Option<bool>) -> Result<UndoResult, String> {
+    let since = journal::parse_timestamp(&since)?;
+    let entries = journal::read_entries()?;
+    let selected: Vec<&JournalEntry> = entries
+        .iter()
+        .filter(|entry| journal::parse_timestamp(&entry.timestamp).is_ok_and(|time| time >= since))
+        .collect();
+
+    undo_entries(&selected, force.unwrap_or(false))
+}
+
+fn undo_entries(entries: &[&JournalEntry], force: bool) -> Result<UndoResult, String> {
+    let store = current_store();
+    let snapshot = EnvSnapshot::load(&*store)?;
+    let changes = journal::plan_undo(&snapshot, entries, force)?;
+
+    let mut warning = None;
+    if !changes.is_empty() {
+        snapshot_before_change()?;
+        warning = transaction::apply_changes(&*store, &snapshot, &changes, ChangeOrigin::Undo)
+            .map_err(|e| e.message(&changes))?;
+        store.notify_changed();
     }
 
-    // 组装结果信息
-    let mut result = format!("导入完成！成功导入 {} 个变量", imported_count);
-    if failed_count > 0 {
-        result.push_str(&format!("，失败 {} 个", failed_count));
-        if failed_vars.len() <= 5 {
-            result.push_str("：\n");
-            result.push_str(&failed_vars.join("\n"));
-        } else {
-            result.push_str("，请检查文件权限或变量格式");
-        }
+    Ok(UndoResult {
+        undone: entries.iter().map(|entry| entry.id).collect(),
+        changes: changes.len(),
+        warning,
+    })
+}
+
+// 获取自动快照所在的文件夹
+#[command]
+pub fn get_snapshot_folder() -> Result<String, String> {
+    Ok(history::history_dir().to_string_lossy().to_string())
+}
+
+#[command]
+pub fn get_snapshot_policy() -> Result<SnapshotPolicy, String> {
+    Ok(history::load_policy())
+}
+
+// 保存快照保留策略，并立即按新策略清理
+#[command]
+pub fn set_snapshot_policy(policy: SnapshotPolicy) -> Result<(), String> {
+    history::save_policy(&policy)
+}
+
+// 获取全部配置方案
+#[command]
+pub fn list_env_profiles() -> Result<Vec<EnvProfile>, String> {
+    Ok(profiles::load_state()?.profiles)
+}
+
+// 新增配置方案，已存在同名方案时覆盖（不影响已激活方案写入的值）
+#[command]
+pub fn save_env_profile(profile: EnvProfile) -> Result<(), String> {
+    let snapshot = EnvSnapshot::load(&*current_store())?;
+    profiles::validate_profile(&profile, &snapshot)?;
+
+    let mut state = profiles::load_state()?;
+    match state
+        .profiles
+        .iter_mut()
+        .find(|existing| existing.name == profile.name)
+    {
+        Some(existing) => *existing = profile,
+        None => state.profiles.push(profile),
     }
+    profiles::save_state(&state)
+}
 
-    Ok(result)
+#[command]
+pub fn delete_env_profile(name: String) -> Result<(), String> {
+    let mut state = profiles::load_state()?;
+    if state
+        .active
+        .as_ref()
+        .is_some_and(|active| active.name == name)
+    {
+        return Err(format!("配置方案 {} 正在使用中，请先停用", name));
+    }
+
+    let count = state.profiles.len();
+    state.profiles.retain(|profile| profile.name != name);
+    if state.profiles.len() == count {
+        return Err(format!("配置方案不存在: {}", name));
+    }
+    profiles::save_state(&state)
 }
 
-// 内部函数：设置环境变量（不发送系统消息）
-fn set_env_var_internal(name: &str, value: &str, is_system: bool) -> Result<(), String> {
-    if is_system {
-        // 设置系统环境变量（需要管理员权限）
-        let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
-        let env_key = hklm
-            .open_subkey_with_flags(
-                "SYSTEM\\CurrentControlSet\\Control\\Session Manager\\Environment",
-                KEY_WRITE,
-            )
-            .map_err(|e| {
-                format!(
-                    "无法打开系统环境变量注册表进行写入（可能需要管理员权限）: {}",
-                    e
-                )
-            })?;
-
-        env_key
-            .set_value(name, &value)
-            .map_err(|e| format!("无法设置系统环境变量: {}", e))?;
-    } else {
-        // 设置用户环境变量
-        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-        let env_key = hkcu
-            .open_subkey_with_flags("Environment", KEY_WRITE)
-            .map_err(|e| format!("无法打开用户环境变量注册表进行写入: {}", e))?;
-
-        env_key
-            .set_value(name, &value)
-            .map_err(|e| format!("无法设置用户环境变量: {}", e))?;
+// 激活配置方案：已有激活的方案时先恢复其修改，整个切换一次性写入
+#[command]
+pub fn activate_env_profile(name: String) -> Result<ActiveProfileStatus, String> {
+    let mut state = profiles::load_state()?;
+    let profile = state
+        .profiles
+        .iter()
+        .find(|profile| profile.name == name)
+        .ok_or_else(|| format!("配置方案不存在: {}", name))?;
+
+    let store = current_store();
+    let snapshot = EnvSnapshot::load(&*store)?;
+    profiles::validate_profile(profile, &snapshot)?;
+    let (changes, active) = profiles::plan_activate(&snapshot, state.active.as_ref(), profile);
+
+    let warning = apply_profile_switch(&*store, &snapshot, &changes)?;
+    let status = ActiveProfileStatus {
+        warning,
+        ..profiles::active_status(&EnvSnapshot::load(&*store)?, &active)
+    };
+    state.active = Some(active);
+    profiles::save_state(&state)?;
+
+    Ok(status)
+}
+
+// 停用当前方案，把它修改过的变量恢复为激活前的值
+#[command]
+pub fn deactivate_env_profile() -> Result<EnvWriteResult, String> {
+    let mut state = profiles::load_state()?;
+    let Some(active) = state.active.as_ref() else {
+        return Err("当前没有激活的配置方案".to_string());
+    };
+
+    let store = current_store();
+    let snapshot = EnvSnapshot::load(&*store)?;
+    let changes = profiles::plan_deactivate(&snapshot, active);
+
+    let warning = apply_profile_switch(&*store, &snapshot, &changes)?;
+    state.active = None;
+    profiles::save_state(&state)?;
+
+    Ok(EnvWriteResult { warning })
+}
+
+// 获取当前激活的配置方案，没有时返回 None
+#[command]
+pub fn get_active_env_profile() -> Result<Option<ActiveProfileStatus>, String> {
+    let state = profiles::load_state()?;
+    match state.active {
+        Some(active) => {
+            let snapshot = EnvSnapshot::load(&*current_store())?;
+            Ok(Some(profiles::active_status(&snapshot, &active)))
+        }
+        None => Ok(None),
     }
+}
 
-    Ok(())
+fn apply_profile_switch(
+    store: &dyn EnvStore,
+    snapshot: &EnvSnapshot,
+    changes: &[EnvChange],
+) -> Result<Option<String>, String> {
+    if changes.is_empty() {
+        return Ok(None);
+    }
+
+    snapshot_before_change()?;
+    let warning = transaction::apply_changes(store, snapshot, changes, ChangeOrigin::Profile)
+        .map_err(|e| e.message(changes))?;
+    store.notify_changed();
+    Ok(warning)
 }
 
-// 检查路径是否存在
+// 检查路径是否存在（先展开其中的变量引用）
 #[command]
 pub async fn check_paths_exist(paths: Vec<String>) -> Result<Vec<bool>, String> {
     use std::path::Path;
 
+    // 读取环境变量失败时按原始路径检查
+    let snapshot = EnvSnapshot::load(&*current_store()).ok();
+    let mut expander = snapshot.as_ref().map(Expander::new);
+
     let mut results = Vec::new();
 
     for path in paths {
-        let path_exists = Path::new(&path).exists();
+        let expanded = match expander.as_mut() {
+            Some(expander) => expander.expand_str(&path, EnvScope::User).value,
+            None => path,
+        };
+        let path_exists = Path::new(&expanded).exists();
         results.push(path_exists);
     }
 
     Ok(results)
 }
 
+// 展开所有环境变量，报告未定义的引用和循环引用
+#[command]
+pub fn expand_env_vars() -> Result<ExpansionReport, String> {
+    let snapshot = EnvSnapshot::load(&*current_store())?;
+    Ok(expand::build_report(&snapshot))
+}
+
+// 分析系统和用户 PATH：重复、失效、非目录条目以及跨作用域重复
+#[command]
+pub fn analyze_path() -> Result<PathAnalysisReport, String> {
+    let snapshot = EnvSnapshot::load(&*current_store())?;
+    Ok(path_analysis::analyze_path(&snapshot))
+}
+
+// 清理 PATH 中的冗余条目：apply 为 false 时只返回预览，为 true 时写入
+#[command]
+pub fn cleanup_path(
+    options: Option<PathCleanupOptions>,
+    apply: bool,
+) -> Result<PathCleanupResult, String> {
+    let store = current_store();
+    let snapshot = EnvSnapshot::load(&*store)?;
+    let mut result = path_cleanup::plan_cleanup(&snapshot, &options.unwrap_or_default());
+
+    if apply && result.has_changes() {
+        snapshot_before_change()?;
+        path_cleanup::apply_cleanup(&*store, &snapshot, &mut result)?;
+        store.notify_changed();
+    }
+
+    Ok(result)
+}
+
+// 按生效的 PATH 和 PATHEXT 解析命令，列出所有匹配并标出实际执行的那个
+#[command]
+pub fn resolve_command(command: String) -> Result<WhichReport, String> {
+    let snapshot = EnvSnapshot::load(&*current_store())?;
+    which::resolve(&snapshot, &command)
+}
+
+// 列出疑似包含令牌、密码等敏感信息的变量
+#[command]
+pub fn find_secret_env_vars() -> Result<Vec<SecretVar>, String> {
+    let snapshot = EnvSnapshot::load(&*current_store())?;
+    Ok(secrets::find_secrets(&snapshot))
+}
+
+// 获取变量之间的引用关系图
+#[command]
+pub fn get_env_dependency_graph() -> Result<DependencyGraph, String> {
+    let snapshot = EnvSnapshot::load(&*current_store())?;
+    Ok(dependency::build_graph(&snapshot))
+}
+
+// 删除或重命名变量前分析影响：列出直接或间接引用它的变量和 PATH 条目
+#[command]
+pub fn analyze_env_var_impact(name: String, is_system: bool) -> Result<ImpactReport, String> {
+    let snapshot = EnvSnapshot::load(&*current_store())?;
+    Ok(dependency::analyze_impact(
+        &snapshot,
+        EnvScope::from_is_system(is_system),
+        &name,
+    ))
+}
+
+// 按列表编辑 PATH 类变量（name 为空时为 PATH）：重新读取当前值，依次应用编辑后一次性写入，
+// 保留原有的值类型，避免覆盖安装程序在界面打开期间做的修改
+#[command]
+pub fn edit_path_list(
+    name: Option<String>,
+    is_system: bool,
+    edits: Vec<PathEdit>,
+) -> Result<PathEditResult, String> {
+    let scope = EnvScope::from_is_system(is_system);
+    let store = current_store();
+    let snapshot = EnvSnapshot::load(&*store)?;
+    let name = name
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| snapshot.flavor.path_var_name().to_string());
+
+    let var = path_edit::apply_edits(&snapshot, scope, &name, &edits)?;
+    let changed = snapshot
+        .get(scope, &name)
+        .is_none_or(|existing| existing.value != var.value);
+
+    let mut warning = None;
+    if changed {
+        snapshot_before_change()?;
+        let changes = [EnvChange::Set {
+            scope,
+            var: var.clone(),
+        }];
+        warning = transaction::apply_changes(&*store, &snapshot, &changes, ChangeOrigin::UiEdit)
+            .map_err(|e| e.message(&changes))?;
+        store.notify_changed();
+    }
+
+    Ok(PathEditResult {
+        entries: path_analysis::split_path_list(&var.value, snapshot.flavor),
+        name: var.name,
+        scope,
+        value: var.value,
+        kind: var.kind,
+        changed,
+        warning,
+    })
+}
+
+// 查找指向已不存在的目录或可执行文件的变量（不仅限于 PATH）
+#[command]
+pub fn find_stale_env_vars() -> Result<StaleScanReport, String> {
+    let snapshot = EnvSnapshot::load(&*current_store())?;
+    Ok(stale::scan_stale(&snapshot))
+}
+
+// 扫描常见位置和指定位置中安装的 JDK、Python、Node 等工具链，并给出变量设置建议
+#[command]
+pub fn discover_toolchains(options: Option<DiscoveryOptions>) -> Result<DiscoveryReport, String> {
+    let snapshot = EnvSnapshot::load(&*current_store())?;
+    Ok(toolchains::discover(
+        &snapshot,
+        &options.unwrap_or_default(),
+    ))
+}
+
+// 获取默认扫描的工具链安装位置
+#[command]
+pub fn get_default_tool_locations() -> Result<Vec<ToolLocation>, String> {
+    Ok(toolchains::default_locations())
+}
+
+// 检查当前环境是否符合团队基线文件（TOML 或 JSON）中的要求
+#[command]
+pub fn check_env_baseline(file_path: String) -> Result<BaselineReport, String> {
+    let manifest = baseline::load_manifest(&file_path)?;
+    let snapshot = EnvSnapshot::load(&*current_store())?;
+    Ok(baseline::evaluate(&snapshot, &manifest, &file_path)?.0)
+}
+
+// 一次性写入基线中缺少的变量和 PATH 条目，返回修复后的检查结果
+#[command]
+pub fn fix_env_baseline(file_path: String) -> Result<BaselineFixResult, String> {
+    let manifest = baseline::load_manifest(&file_path)?;
+    let store = current_store();
+    let snapshot = EnvSnapshot::load(&*store)?;
+    let (_, changes) = baseline::evaluate(&snapshot, &manifest, &file_path)?;
+
+    let mut warning = None;
+    if !changes.is_empty() {
+        snapshot_before_change()?;
+        warning = transaction::apply_changes(&*store, &snapshot, &changes, ChangeOrigin::Baseline)
+            .map_err(|e| e.message(&changes))?;
+        store.notify_changed();
+    }
+
+    let snapshot = EnvSnapshot::load(&*store)?;
+    Ok(BaselineFixResult {
+        changes: changes.len(),
+        report: baseline::evaluate(&snapshot, &manifest, &file_path)?.0,
+        warning,
+    })
+}
+
+// 获取内置的校验规则
+#[command]
+pub fn get_builtin_validation_rules() -> Result<Vec<ValidationRule>, String> {
+    Ok(validation::builtin_rules())
+}
+
+// 获取自定义校验规则和被禁用的内置规则
+#[command]
+pub fn get_validation_settings() -> Result<RuleSettings, String> {
+    validation::load_settings()
+}
+
+#[command]
+pub fn set_validation_settings(settings: RuleSettings) -> Result<(), String> {
+    validation::save_settings(&settings)
+}
+
+// 按校验规则检查所有变量，生成健康报告
+#[command]
+pub fn check_env_health() -> Result<HealthReport, String> {
+    let snapshot = EnvSnapshot::load(&*current_store())?;
+    Ok(validation::check_health(
+        &snapshot,
+        &validation::load_settings()?,
+    ))
+}
+
+// 将健康报告导出为 JSON 文件，未指定路径时保存到文档文件夹
+#[command]
+pub async fn export_env_health_report(file_path: String) -> Result<String, String> {
+    use chrono::Local;
+    use std::fs;
+
+    let final_path = if file_path.is_empty() {
+        let documents_dir =
+            dirs::document_dir().ok_or_else(|| "无法获取文档文件夹路径".to_string())?;
+        let timestamp = Local::now().format("%Y%m%d_%H%M%S");
+        documents_dir.join(format!("环境变量健康报告_{}.json", timestamp))
+    } else {
+        std::path::PathBuf::from(&file_path)
+    };
+
+    if let Some(parent) = final_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+    }
+
+    let report = check_env_health()?;
+    let content =
+        serde_json::to_string_pretty(&report).map_err(|e| format!("序列化数据失败: {}", e))?;
+    fs::write(&final_path, content).map_err(|e| format!("写入文件失败: {}", e))?;
+
+    Ok(final_path.to_string_lossy().to_string())
+}
+
 // 配置文件信息结构
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ConfigFileInfo {
     pub file_path: String,
     pub file_name: String,
     pub export_time: String,
-    pub version: String,
+    /// 导出时的应用版本
+    pub app_version: Option<String>,
+    /// 文件的结构版本，dotenv / .reg 文件为空
+    pub schema_version: Option<u32>,
+    pub hostname: Option<String>,
+    pub username: Option<String>,
+    pub os: Option<String>,
+    pub checksum: ChecksumStatus,
     pub system_vars_count: usize,
     pub user_vars_count: usize,
     pub file_size: u64,
     pub created_time: String,
     pub modified_time: String,
+    /// 是否为加密备份，加密备份的信息来自明文元数据
+    #[serde(default)]
+    pub encrypted: bool,
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 固定的备份不会被清理
+    #[serde(default)]
+    pub pinned: bool,
 }
 
 // 扫描指定文件夹中的环境变量配置文件
 #[command]
 pub async fn scan_config_files(folder_path: String) -> Result<Vec<ConfigFileInfo>, String> {
-    use std::fs;
     use std::path::Path;
 
     let folder = Path::new(&folder_path);
@@ -548,21 +1007,21 @@ pub async fn scan_config_files(folder_path: String) -> Result<Vec<ConfigFileInfo
 
     let mut config_files = Vec::new();
 
-    // 读取文件夹中的所有 .json 文件
-    let entries = fs::read_dir(folder).map_err(|e| format!("无法读取文件夹: {}", e))?;
-
-    for entry in entries {
-        let entry = entry.map_err(|e| format!("读取文件项失败: {}", e))?;
-        let path = entry.path();
-
-        // 只处理 .json 文件
-        if !path.is_file() || path.extension().unwrap_or_default() != "json" {
-            continue;
-        }
+    // 索引文件损坏时仍然列出备份，只是不带备注和标签
+    let mut annotations = backup_folder::load_annotations(folder).unwrap_or_default();
 
+    // 只处理 .json、.env 和 .reg 文件
+    for path in backup_folder::list_files(folder)? {
         // 尝试解析文件内容
         match parse_config_file(&path) {
-            Ok(config_info) => config_files.push(config_info),
+            Ok(mut config_info) => {
+                if let Some(annotation) = annotations.remove(&config_info.file_name) {
+                    config_info.note = annotation.note;
+                    config_info.tags = annotation.tags;
+                    config_info.pinned = annotation.pinned;
+                }
+                config_files.push(config_info)
+            }
             Err(_) => continue, // 忽略无法解析的文件
         }
     }
@@ -584,58 +1043,6 @@ fn parse_config_file(file_path: &std::path::Path) -> Result<ConfigFileInfo, Stri
         .to_string_lossy()
         .to_string();
 
-    // 读取文件内容
-    let json_content = fs::read_to_string(file_path).map_err(|e| format!("读取文件失败: {}", e))?;
-
-    // 首先尝试解析新格式（包含 export_info）
-    let import_data: EnvVarExport =
-        if let Ok(data) = serde_json::from_str::<EnvVarExport>(&json_content) {
-            data
-        } else {
-            // 如果失败，尝试解析旧格式（直接是 system/user 变量）
-            match serde_json::from_str::<serde_json::Value>(&json_content) {
-                Ok(json_value) => {
-                    // 旧格式：system/user 字段
-                    if let (Some(system), Some(user)) = (
-                        json_value
-                            .get("system")
-                            .and_then(|v| serde_json::from_value::<Vec<EnvVar>>(v.clone()).ok()),
-                        json_value
-                            .get("user")
-                            .and_then(|v| serde_json::from_value::<Vec<EnvVar>>(v.clone()).ok()),
-                    ) {
-                        let export_time = json_value
-                            .get("exportTime")
-                            .and_then(|v| v.as_str())
-                            .map(|s| s.to_string())
-                            .unwrap_or_else(|| {
-                                // 尝试从文件名中提取时间戳
-                                extract_timestamp_from_filename(&file_name)
-                                    .unwrap_or_else(|| "未知".to_string())
-                            });
-
-                        let version = json_value
-                            .get("appVersion")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("1.0")
-                            .to_string();
-
-                        EnvVarExport {
-                            export_info: ExportInfo {
-                                export_time,
-                                version,
-                            },
-                            system_vars: system,
-                            user_vars: user,
-                        }
-                    } else {
-                        return Err("无法解析环境变量数据".to_string());
-                    }
-                }
-                Err(e) => return Err(format!("无法解析 JSON 文件: {}", e)),
-            }
-        };
-
     // 获取文件元数据
     let metadata = fs::metadata(file_path).map_err(|e| format!("获取文件元数据失败: {}", e))?;
 
@@ -662,20 +1069,98 @@ fn parse_config_file(file_path: &std::path::Path) -> Result<ConfigFileInfo, Stri
         .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
         .unwrap_or_else(|| "未知".to_string());
 
-    // 使用导出时间（简化格式）
-    let export_time = import_data.export_info.export_time.clone();
-
-    Ok(ConfigFileInfo {
-        file_path: file_path.to_string_lossy().to_string(),
-        file_name,
-        export_time,
-        version: import_data.export_info.version,
-        system_vars_count: import_data.system_vars.len(),
-        user_vars_count: import_data.user_vars.len(),
-        file_size,
-        created_time,
-        modified_time,
-    })
+    // 读取文件内容，加密备份只读取明文元数据，不需要密码
+    let bytes = fs::read(file_path).map_err(|e| format!("读取文件失败: {}", e))?;
+    let mut info = match backup::parse(file_path, &bytes)? {
+        BackupFile::Plain(loaded) => {
+            let export_info = loaded.data.export_info;
+            ConfigFileInfo {
+                file_path: file_path.to_string_lossy().to_string(),
+                file_name,
+                export_time: export_info.export_time,
+                app_version: export_info.app_version,
+                schema_version: loaded.schema_version,
+                hostname: export_info.hostname,
+                username: export_info.username,
+                os: export_info.os,
+                checksum: loaded.checksum,
+                system_vars_count: loaded.data.system_vars.len(),
+                user_vars_count: loaded.data.user_vars.len(),
+                file_size,
+                created_time,
+                modified_time,
+                encrypted: false,
+                note: None,
+                tags: Vec::new(),
+                pinned: false,
+            }
+        }
+        BackupFile::Encrypted(envelope) => {
+            let metadata = envelope.metadata()?;
+            ConfigFileInfo {
+                file_path: file_path.to_string_lossy().to_string(),
+                file_name,
+                export_time: metadata.export_time,
+                app_version: Some(metadata.version).filter(|version| !version.is_empty()),
+                schema_version: Some(metadata.schema_version.unwrap_or(1)),
+                // 来源信息在密文中，解密前未知
+                hostname: None,
+                username: None,
+                os: None,
+                checksum: ChecksumStatus::Encrypted,
+                system_vars_count: metadata.system_vars_count,
+                user_vars_count: metadata.user_vars_count,
+                file_size,
+                created_time,
+                modified_time,
+                encrypted: true,
+                note: None,
+                tags: Vec::new(),
+                pinned: false,
+            }
+        }
+    };
+
+    // 最早的备份格式可能没有导出时间，尝试从文件名中提取
+    if info.export_time == "未知" {
+        if let Some(export_time) = extract_timestamp_from_filename(&info.file_name) {
+            info.export_time = export_time;
+        }
+    }
+
+    Ok(info)
+}
+
+// 设置备份的备注、标签和固定状态（固定的备份不会被清理）
+#[command]
+pub fn set_backup_annotation(
+    file_path: String,
+    annotation: BackupAnnotation,
+) -> Result<(), String> {
+    backup_folder::set_annotation(std::path::Path::new(&file_path), annotation)
+}
+
+// 按策略清理文件夹中的备份，dry_run 为 true 时只返回将要删除的备份
+#[command]
+pub fn prune_backups(
+    folder_path: String,
+    policy: BackupPrunePolicy,
+    dry_run: Option<bool>,
+) -> Result<BackupPruneResult, String> {
+    backup_folder::prune(
+        std::path::Path::new(&folder_path),
+        &policy,
+        dry_run.unwrap_or(false),
+    )
+}
+
+// 校验文件夹中的全部备份，找出损坏或被修改的文件；加密备份提供密码时一并校验
+#[command]
+pub fn validate_backups(
+    folder_path: String,
+    passphrase: Option<String>,
+) -> Result<BackupValidationReport, String> {
+    backup_folder::validate(std::path::Path::new(&folder_path), passphrase.as_deref())
 }
 
 // 从文件名中提取时间戳
@@ -0,0 +1,348 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+use super::encryption::{self, EncryptedBackup};
+use super::formats;
+use super::secrets::SecretHandling;
+use super::{EnvVar, EnvVarExport, ExportInfo};
+
+/// 当前写入的备份结构版本
+///
+/// - 0：最早的前端导出格式，`system` / `user` / `exportTime` / `appVersion`
+/// - 1：`export_info` / `system_vars` / `user_vars`，`export_info.version` 为固定的 "1.0"
+///   或前端写入的应用版本
+/// - 2：`export_info` 带结构版本、应用版本、来源计算机信息和校验和
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// 读取加密备份但未提供密码时返回的错误，前端据此提示输入密码
+pub const PASSPHRASE_REQUIRED: &str = "该备份已加密，请输入密码";
+
+const CHECKSUM_PREFIX: &str = "sha256:";
+
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+// MIGRATIONS[n] 把结构版本 n 升级到 n + 1
+const MIGRATIONS: &[Migration] = &[migrate_v0, migrate_v1];
+
+/// 备份内容的校验结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecksumStatus {
+    Verified,
+    /// 旧版本的备份或 dotenv / .reg 文件，没有校验和
+    Missing,
+    /// 内容与校验和不符，文件已损坏或被手动修改
+    Mismatch,
+    /// 加密备份在解密时校验
+    Encrypted,
+}
+
+/// 读取并升级到当前结构后的备份
+#[derive(Debug)]
+pub struct LoadedBackup {
+    pub data: EnvVarExport,
+    /// 文件原本的结构版本，dotenv / .reg 文件为 None
+    pub schema_version: Option<u32>,
+    pub checksum: ChecksumStatus,
+}
+
+#[derive(Debug)]
+pub enum BackupFile {
+    Plain(LoadedBackup),
+    Encrypted(EncryptedBackup),
+}
+
+/// 识别备份文件：dotenv / .reg 文件、加密备份和各结构版本的 JSON 备份
+pub fn parse(path: &Path, bytes: &[u8]) -> Result<BackupFile, String> {
+    if let Some(format) = formats::detect_import_format(path, bytes) {
+        return Ok(BackupFile::Plain(LoadedBackup {
+            data: formats::parse_import(format, path, bytes)?,
+            schema_version: None,
+            checksum: ChecksumStatus::Missing,
+        }));
+    }
+    if let Some(envelope) = encryption::parse_envelope(bytes)? {
+        return Ok(BackupFile::Encrypted(envelope));
+    }
+    parse_json(bytes).map(BackupFile::Plain)
+}
+
+/// 读取用于导入或对比的备份，加密备份用 passphrase 解密；校验和不符时拒绝读取
+pub fn read(file_path: &str, passphrase: Option<&str>) -> Result<EnvVarExport, String> {
+    let path = Path::new(file_path);
+    let bytes = fs::read(path).map_err(|e| format!("读取文件失败: {}", e))?;
+
+    let loaded = match parse(path, &bytes)? {
+        BackupFile::Plain(loaded) => loaded,
+        BackupFile::Encrypted(envelope) => {
+            let passphrase = passphrase.ok_or_else(|| PASSPHRASE_REQUIRED.to_string())?;
+            parse_json(&encryption::decrypt(&envelope, passphrase)?)?
+        }
+    };
+    if loaded.checksum == ChecksumStatus::Mismatch {
+        return Err("备份文件校验失败，文件可能已损坏或被手动修改".to_string());
+    }
+
+    Ok(loaded.data)
+}
+
+/// 解析 JSON 备份，按顺序执行迁移升级到当前结构，并校验校验和
+pub fn parse_json(bytes: &[u8]) -> Result<LoadedBackup, String> {
+    let mut value: Value =
+        serde_json::from_slice(bytes).map_err(|e| format!("解析 JSON 文件失败: {}", e))?;
+
+    let original = schema_version(&value)?;
+    if original > SCHEMA_VERSION {
+        return Err(format!(
+            "备份文件由更新版本的应用创建（结构版本 {}），请升级到最新版本后再试",
+            original
+        ));
+    }
+    for migrate in &MIGRATIONS[original as usize..] {
+        migrate(&mut value)?;
+    }
+
+    let data: EnvVarExport =
+        serde_json::from_value(value).map_err(|e| format!("备份文件格式不正确: {}", e))?;
+    let checksum = match &data.export_info.checksum {
+        None => ChecksumStatus::Missing,
+        Some(expected) if *expected == checksum(&data)? => ChecksumStatus::Verified,
+        Some(_) => ChecksumStatus::Mismatch,
+    };
+
+    Ok(LoadedBackup {
+        data,
+        schema_version: Some(original),
+        checksum,
+    })
+}
+
+/// 写入校验和后序列化为 JSON
+pub fn to_json(data: &EnvVarExport) -> Result<String, String> {
+    let mut data = data.clone();
+    data.export_info.checksum = Some(checksum(&data)?);
+    serde_json::to_string_pretty(&data).map_err(|e| format!("序列化数据失败: {}", e))
+}
+
+/// 当前环境的导出数据，带上应用版本和来源计算机信息
+pub fn new_export(system_vars: Vec<EnvVar>, user_vars: Vec<EnvVar>) -> EnvVarExport {
+    EnvVarExport {
+        export_info: ExportInfo {
+            app_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            hostname: whoami::fallible::hostname().ok(),
+            username: Some(whoami::username()),
+            os: Some(whoami::distro()),
+            ..imported_info(chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string())
+        },
+        system_vars,
+        user_vars,
+    }
+}
+
+/// 从其他来源（dotenv、.reg 文件）转换来的数据，没有来源信息
+pub fn imported_info(export_time: String) -> ExportInfo {
+    ExportInfo {
+        schema_version: SCHEMA_VERSION,
+        export_time,
+        app_version: None,
+        hostname: None,
+        username: None,
+        os: None,
+        secrets: SecretHandling::Include,
+        secret_vars: Vec::new(),
+        checksum: None,
+    }
+}
+
+// 对除校验和外的全部内容按结构序列化后计算，字段顺序和格式化方式不影响结果
+fn checksum(data: &EnvVarExport) -> Result<String, String> {
+    let mut data = data.clone();
+    data.export_info.checksum = None;
+    let bytes = serde_json::to_vec(&data).map_err(|e| format!("序列化数据失败: {}", e))?;
+
+    let digest = Sha256::digest(&bytes);
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    Ok(format!("{}{}", CHECKSUM_PREFIX, hex))
+}
+
+fn schema_version(value: &Value) -> Result<u32, String> {
+    if let Some(info) = value.get("export_info") {
+        return match info.get("schema_version") {
+            None => Ok(1),
+            Some(version) => version
+                .as_u64()
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or_else(|| format!("备份文件的结构版本无效: {}", version)),
+        };
+    }
+    if value.get("system").is_some() && value.get("user").is_some() {
+        return Ok(0);
+    }
+    Err("JSON文件格式不正确，缺少必要的环境变量数据".to_string())
+}
+
+// system / user / exportTime / appVersion -> export_info / system_vars / user_vars
+fn migrate_v0(value: &mut Value) -> Result<(), String> {
+    let text = |key: &str, default: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or(default)
+            .to_string()
+    };
+    let export_time = text("exportTime", "未知");
+    let version = text("appVersion", "1.0");
+
+    *value = json!({
+        "export_info": {
+            "export_time": export_time,
+            "version": version,
+        },
+        "system_vars": value["system"].take(),
+        "user_vars": value["user"].take(),
+    });
+    Ok(())
+}
+
+// version 中的固定值 "1.0" 不是应用版本，其他值是前端导出时写入的应用版本
+fn migrate_v1(value: &mut Value) -> Result<(), String> {
+    let info: &mut Map<String, Value> = value
+        .get_mut("export_info")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| "备份文件缺少 export_info".to_string())?;
+
+    let app_version = info
+        .remove("version")
+        .filter(|version| version.as_str().is_some_and(|version| version != "1.0"));
+    if let Some(app_version) = app_version {
+        info.insert("app_version".to_string(), app_version);
+    }
+    info.insert("schema_version".to_string(), json!(2));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env_var::EnvValueKind;
+
+    fn var(name: &str, value: &str) -> EnvVar {
+        EnvVar {
+            name: name.to_string(),
+            value: value.to_string(),
+            kind: EnvValueKind::infer(value),
+        }
+    }
+
+    #[test]
+    fn migrates_v0_frontend_exports() {
+        let loaded = parse_json(
+            br#"{
+                "exportTime": "2023-01-01 10:00:00",
+                "appVersion": "0.3.0",
+                "system": [{ "name": "ComSpec", "value": "C:\\Windows\\system32\\cmd.exe" }],
+                "user": [{ "name": "TOOLS", "value": "%USERPROFILE%\\tools" }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(loaded.schema_version, Some(0));
+        assert_eq!(loaded.checksum, ChecksumStatus::Missing);
+        let info = &loaded.data.export_info;
+        assert_eq!(info.schema_version, SCHEMA_VERSION);
+        assert_eq!(info.export_time, "2023-01-01 10:00:00");
+        assert_eq!(info.app_version.as_deref(), Some("0.3.0"));
+        assert_eq!(
+            loaded.data.system_vars,
+            [var("ComSpec", "C:\\Windows\\system32\\cmd.exe")]
+        );
+        // 没有 kind 字段时按值推断
+        assert_eq!(loaded.data.user_vars[0].kind, EnvValueKind::ExpandString);
+
+        let loaded = parse_json(br#"{ "system": [], "user": [] }"#).unwrap();
+        assert_eq!(loaded.data.export_info.export_time, "未知");
+        assert_eq!(loaded.data.export_info.app_version, None);
+    }
+
+    #[test]
+    fn migrates_v1_exports() {
+        let loaded = parse_json(
+            br#"{
+                "export_info": { "export_time": "2024-03-01 08:00:00", "version": "1.0" },
+                "system_vars": [],
+                "user_vars": [{ "name": "JAVA_HOME", "value": "C:\\jdk", "kind": "string" }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(loaded.schema_version, Some(1));
+        assert_eq!(loaded.checksum, ChecksumStatus::Missing);
+        assert_eq!(loaded.data.export_info.schema_version, SCHEMA_VERSION);
+        assert_eq!(loaded.data.export_info.app_version, None);
+        assert_eq!(loaded.data.user_vars, [var("JAVA_HOME", "C:\\jdk")]);
+
+        // 前端写入的应用版本保留为 app_version
+        let loaded = parse_json(
+            br#"{"export_info":{"export_time":"","version":"0.5.2"},"system_vars":[],"user_vars":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            loaded.data.export_info.app_version.as_deref(),
+            Some("0.5.2")
+        );
+    }
+
+    #[test]
+    fn verifies_checksum_of_current_exports() {
+        let data = new_export(
+            vec![var("ComSpec", "%SystemRoot%\\system32\\cmd.exe")],
+            vec![var("JAVA_HOME", "C:\\jdk")],
+        );
+        let json = to_json(&data).unwrap();
+
+        let loaded = parse_json(json.as_bytes()).unwrap();
+        assert_eq!(loaded.schema_version, Some(SCHEMA_VERSION));
+        assert_eq!(loaded.checksum, ChecksumStatus::Verified);
+        assert_eq!(loaded.data.user_vars, data.user_vars);
+
+        // 字段顺序和格式化方式不影响校验
+        let compact: Value = serde_json::from_str(&json).unwrap();
+        let loaded = parse_json(compact.to_string().as_bytes()).unwrap();
+        assert_eq!(loaded.checksum, ChecksumStatus::Verified);
+    }
+
+    #[test]
+    fn reports_hand_edited_values() {
+        let data = new_export(Vec::new(), vec![var("JAVA_HOME", "C:\\jdk")]);
+        let json = to_json(&data).unwrap().replace("C:\\\\jdk", "C:\\\\jdk8");
+
+        let loaded = parse_json(json.as_bytes()).unwrap();
+        assert_eq!(loaded.data.user_vars[0].value, "C:\\jdk8");
+        assert_eq!(loaded.checksum, ChecksumStatus::Mismatch);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("backup.json");
+        fs::write(&path, json).unwrap();
+        assert_eq!(
+            read(&path.to_string_lossy(), None).unwrap_err(),
+            "备份文件校验失败，文件可能已损坏或被手动修改"
+        );
+    }
+
+    #[test]
+    fn rejects_newer_or_unrecognized_files() {
+        let error = parse_json(
+            br#"{"export_info":{"schema_version":3,"export_time":""},"system_vars":[],"user_vars":[]}"#,
+        )
+        .unwrap_err();
+        assert!(error.contains("结构版本 3"));
+
+        assert_eq!(
+            parse_json(br#"{"vars":[]}"#).unwrap_err(),
+            "JSON文件格式不正确，缺少必要的环境变量数据"
+        );
+    }
+}
@@ -0,0 +1,361 @@
+use chrono::{DateTime, Duration, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::backup::{self, BackupFile, ChecksumStatus};
+use super::encryption;
+use super::file_store::write_atomic;
+
+/// 备注、标签和固定状态保存在备份文件夹中的这个文件里，随文件夹一起移动或共享
+pub const INDEX_FILE_NAME: &str = ".my-tools-backups.json";
+
+/// 单个备份的备注、标签和固定状态
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupAnnotation {
+    pub note: Option<String>,
+    pub tags: Vec<String>,
+    /// 固定的备份不会被清理
+    pub pinned: bool,
+}
+
+// 按文件名记录，文件夹移动后仍然有效
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct BackupIndex {
+    backups: BTreeMap<String, BackupAnnotation>,
+}
+
+/// 清理策略，两个条件同时设置时，最新的 keep_latest 个之外且超过 older_than_days 天的备份才会被删除
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupPrunePolicy {
+    pub older_than_days: Option<u32>,
+    pub keep_latest: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PrunedBackup {
+    pub file_name: String,
+    pub file_path: String,
+    pub export_time: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupPruneResult {
+    pub dry_run: bool,
+    /// 已删除（预览时为将要删除）的备份
+    pub deleted: Vec<PrunedBackup>,
+    pub kept: usize,
+    /// 因固定而保留的备份数量
+    pub pinned: usize,
+    /// 删除失败的备份和更新备份索引时出现的错误，其他备份仍会继续清理
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BackupHealth {
+    Valid,
+    /// 没有校验和（旧版本的备份、dotenv / .reg 文件）或未提供密码的加密备份
+    Unverified {
+        reason: String,
+    },
+    /// 无法解析、校验失败或无法解密
+    Corrupted {
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupCheck {
+    pub file_name: String,
+    pub file_path: String,
+    #[serde(flatten)]
+    pub health: BackupHealth,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupValidationReport {
+    pub checked: usize,
+    pub valid: usize,
+    pub unverified: usize,
+    pub corrupted: usize,
+    pub results: Vec<BackupCheck>,
+}
+
+/// 文件夹中可能是备份的文件（.json、.env、.reg 以及 .env.* 文件），不包括索引文件
+pub fn list_files(folder: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir(folder).map_err(|e| format!("无法读取文件夹: {}", e))?;
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("读取文件项失败: {}", e))?;
+        let path = entry.path();
+        let file_name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        let extension = path
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_lowercase();
+        let is_dotenv = file_name.starts_with(".env");
+        if !path.is_file()
+            || file_name == INDEX_FILE_NAME
+            || !(matches!(extension.as_str(), "json" | "env" | "reg") || is_dotenv)
+        {
+            continue;
+        }
+        files.push(path);
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// 读取文件夹中全部备份的备注、标签和固定状态，键为文件名
+pub fn load_annotations(folder: &Path) -> Result<BTreeMap<String, BackupAnnotation>, String> {
+    Ok(load_index(folder)?.backups)
+}
+
+/// 设置备份的备注、标签和固定状态，全部为空时移除该备份的记录
+pub fn set_annotation(file_path: &Path, annotation: BackupAnnotation) -> Result<(), String> {
+    if !file_path.is_file() {
+        return Err(format!("备份文件不存在: {}", file_path.display()));
+    }
+    let (folder, file_name) = split_path(file_path)?;
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in annotation.tags.iter().map(|tag| tag.trim()) {
+        if !tag.is_empty() && !tags.iter().any(|existing| existing == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    let annotation = BackupAnnotation {
+        note: annotation
+            .note
+            .map(|note| note.trim().to_string())
+            .filter(|note| !note.is_empty()),
+        tags,
+        pinned: annotation.pinned,
+    };
+
+    let mut index = load_index(folder)?;
+    if annotation == BackupAnnotation::default() {
+        index.backups.remove(&file_name);
+    } else {
+        index.backups.insert(file_name, annotation);
+    }
+    save_index(folder, &index)
+}
+
+/// 按策略清理备份，固定的备份、无法识别导出时间的备份和不是本应用导出的文件
+/// （dotenv、.reg、无法解析的文件）不会被删除
+pub fn prune(
+    folder: &Path,
+    policy: &BackupPrunePolicy,
+    dry_run: bool,
+) -> Result<BackupPruneResult, String> {
+    if policy.older_than_days.is_none() && policy.keep_latest.is_none() {
+        return Err("请至少设置一个清理条件".to_string());
+    }
+
+    let mut index = load_index(folder)?;
+    let mut backups: Vec<(PathBuf, String, NaiveDateTime)> = Vec::new();
+    let mut pinned = 0;
+    let mut kept = 0;
+    for path in list_files(folder)? {
+        let Some(export_time) = export_time(&path) else {
+            continue;
+        };
+        let file_name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        if index
+            .backups
+            .get(&file_name)
+            .is_some_and(|annotation| annotation.pinned)
+        {
+            pinned += 1;
+            continue;
+        }
+        // 无法确定备份时间时不删除
+        let Some(export_time) = parse_export_time(&export_time) else {
+            kept += 1;
+            continue;
+        };
+        backups.push((path, file_name, export_time));
+    }
+
+    // 最新的在前
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.2));
+    let cutoff = policy
+        .older_than_days
+        .map(|days| Local::now().naive_local() - Duration::days(i64::from(days)));
+    let keep_latest = policy.keep_latest.unwrap_or(0);
+
+    let mut deleted = Vec::new();
+    let mut errors = Vec::new();
+    kept += pinned;
+    for (position, (path, file_name, export_time)) in backups.into_iter().enumerate() {
+        let expired = cutoff.is_none_or(|cutoff| export_time < cutoff);
+        if position < keep_latest || !expired {
+            kept += 1;
+            continue;
+        }
+
+        if !dry_run {
+            if let Err(e) = fs::remove_file(&path) {
+                errors.push(format!("删除备份 {} 失败: {}", file_name, e));
+                kept += 1;
+                continue;
+            }
+            index.backups.remove(&file_name);
+        }
+        deleted.push(PrunedBackup {
+            file_path: path.to_string_lossy().to_string(),
+            export_time: export_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+            file_name,
+        });
+    }
+
+    // 已删除的文件无法恢复，索引写入失败时同样作为错误返回给调用方
+    if !dry_run && !deleted.is_empty() {
+        if let Err(e) = save_index(folder, &index) {
+            errors.push(e);
+        }
+    }
+
+    Ok(BackupPruneResult {
+        dry_run,
+        deleted,
+        kept,
+        pinned,
+        errors,
+    })
+}
+
+/// 校验文件夹中的全部备份，加密备份提供密码时解密校验
+pub fn validate(folder: &Path, passphrase: Option<&str>) -> Result<BackupValidationReport, String> {
+    let mut results = Vec::new();
+    for path in list_files(folder)? {
+        results.push(BackupCheck {
+            file_name: path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            file_path: path.to_string_lossy().to_string(),
+            health: check_file(&path, passphrase),
+        });
+    }
+
+    let count = |matches: fn(&BackupHealth) -> bool| {
+        results
+            .iter()
+            .filter(|result| matches(&result.health))
+            .count()
+    };
+    Ok(BackupValidationReport {
+        checked: results.len(),
+        valid: count(|health| matches!(health, BackupHealth::Valid)),
+        unverified: count(|health| matches!(health, BackupHealth::Unverified { .. })),
+        corrupted: count(|health| matches!(health, BackupHealth::Corrupted { .. })),
+        results,
+    })
+}
+
+fn check_file(path: &Path, passphrase: Option<&str>) -> BackupHealth {
+    let corrupted = |error: String| BackupHealth::Corrupted { error };
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => return corrupted(format!("读取文件失败: {}", e)),
+    };
+
+    let loaded = match backup::parse(path, &bytes) {
+        Ok(BackupFile::Plain(loaded)) => loaded,
+        Ok(BackupFile::Encrypted(envelope)) => {
+            let Some(passphrase) = passphrase else {
+                return BackupHealth::Unverified {
+                    reason: "加密备份，需要密码才能校验".to_string(),
+                };
+            };
+            match encryption::decrypt(&envelope, passphrase)
+                .and_then(|plaintext| backup::parse_json(&plaintext))
+            {
+                Ok(loaded) => loaded,
+                Err(e) => return corrupted(e),
+            }
+        }
+        Err(e) => return corrupted(e),
+    };
+
+    match loaded.checksum {
+        ChecksumStatus::Verified => BackupHealth::Valid,
+        ChecksumStatus::Mismatch => corrupted("校验失败，文件可能已损坏或被手动修改".to_string()),
+        ChecksumStatus::Missing | ChecksumStatus::Encrypted => BackupHealth::Unverified {
+            reason: "文件没有校验和".to_string(),
+        },
+    }
+}
+
+// 本应用导出的备份（含加密备份）中记录的导出时间，不是本应用导出的文件返回 None
+fn export_time(path: &Path) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    let export_time = match backup::parse(path, &bytes).ok()? {
+        BackupFile::Plain(loaded) => {
+            loaded.schema_version?;
+            loaded.data.export_info.export_time
+        }
+        BackupFile::Encrypted(envelope) => envelope.metadata().ok()?.export_time,
+    };
+    Some(export_time)
+}
+
+// 导出时间为本地时间（%Y-%m-%d %H:%M:%S）或 RFC 3339 格式，后者转换为本地时间
+fn parse_export_time(export_time: &str) -> Option<NaiveDateTime> {
+    let export_time = export_time.trim();
+    NaiveDateTime::parse_from_str(export_time, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(export_time)
+                .ok()
+                .map(|time| time.with_timezone(&Local).naive_local())
+        })
+}
+
+fn split_path(file_path: &Path) -> Result<(&Path, String), String> {
+    let folder = file_path
+        .parent()
+        .ok_or_else(|| format!("无效的备份路径: {}", file_path.display()))?;
+    let file_name = file_path
+        .file_name()
+        .ok_or_else(|| format!("无效的备份路径: {}", file_path.display()))?
+        .to_string_lossy()
+        .to_string();
+    Ok((folder, file_name))
+}
+
+// 索引文件不存在时为空，无法解析时返回错误，避免写入时覆盖掉已有的备注
+fn load_index(folder: &Path) -> Result<BackupIndex, String> {
+    let path = folder.join(INDEX_FILE_NAME);
+    if !path.exists() {
+        return Ok(BackupIndex::default());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("读取备份索引失败: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("解析备份索引失败: {}", e))
+}
+
+fn save_index(folder: &Path, index: &BackupIndex) -> Result<(), String> {
+    let content =
+        serde_json::to_string_pretty(index).map_err(|e| format!("序列化数据失败: {}", e))?;
+    write_atomic(&folder.join(INDEX_FILE_NAME), &content)
+}
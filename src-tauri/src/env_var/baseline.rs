@@ -0,0 +1,547 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use super::expand::{self, Expander};
+use super::path_analysis::{join_path_list, normalize_path, split_path_list};
+use super::store::{EnvFlavor, EnvScope, EnvSnapshot};
+use super::transaction::EnvChange;
+use super::{EnvValueKind, EnvVar};
+
+/// 团队基线中要求存在的变量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequiredVar {
+    pub name: String,
+    /// 要求所在的作用域，为空时检查实际生效的值，修复时写入用户变量
+    #[serde(default)]
+    pub scope: Option<EnvScope>,
+    /// 允许的值（正则表达式，原始值或展开后的值匹配即可）
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// 修复时写入的值，为空时只检查不修复
+    #[serde(default)]
+    pub value: Option<String>,
+    #[serde(default)]
+    pub kind: Option<EnvValueKind>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// 团队基线中要求出现在 PATH 里的条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequiredPathEntry {
+    pub entry: String,
+    /// 要求所在的作用域，为空时检查实际生效的 PATH，修复时写入用户 PATH
+    #[serde(default)]
+    pub scope: Option<EnvScope>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// 提交在项目仓库中的团队环境基线（TOML 或 JSON）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BaselineManifest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub variables: Vec<RequiredVar>,
+    pub path: Vec<RequiredPathEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RequirementTarget {
+    Variable { name: String },
+    PathEntry { entry: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RequirementStatus {
+    Met,
+    Missing,
+    /// 变量存在但值不符合 pattern
+    Mismatch {
+        actual: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RequirementResult {
+    #[serde(flatten)]
+    pub target: RequirementTarget,
+    pub scope: Option<EnvScope>,
+    pub description: Option<String>,
+    #[serde(flatten)]
+    pub status: RequirementStatus,
+    /// 修复时将执行的操作，无法自动修复时为空
+    pub fix: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BaselineReport {
+    pub name: Option<String>,
+    pub file_path: String,
+    pub results: Vec<RequirementResult>,
+    pub met: usize,
+    pub violated: usize,
+    /// 违反的要求中可以自动修复的数量
+    pub fixable: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BaselineFixResult {
+    /// 实际写入的变量数量
+    pub changes: usize,
+    /// 修复后重新检查的结果
+    pub report: BaselineReport,
+    /// 修改已生效但出现的问题（如修改日志写入失败）
+    pub warning: Option<String>,
+}
+
+/// 读取基线文件，.toml 按 TOML 解析，其余按 JSON 解析
+pub fn load_manifest(file_path: &str) -> Result<BaselineManifest, String> {
+    let content = fs::read_to_string(file_path).map_err(|e| format!("读取文件失败: {}", e))?;
+    let is_toml = Path::new(file_path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+
+    let manifest: BaselineManifest = if is_toml {
+        toml::from_str(&content).map_err(|e| format!("解析 TOML 文件失败: {}", e))?
+    } else {
+        serde_json::from_str(&content).map_err(|e| format!("解析 JSON 文件失败: {}", e))?
+    };
+
+    for var in &manifest.variables {
+        if var.name.trim().is_empty() {
+            return Err("基线中的变量名不能为空".to_string());
+        }
+        if let Some(pattern) = &var.pattern {
+            Regex::new(pattern)
+                .map_err(|e| format!("变量 {} 的正则表达式错误: {}", var.name, e))?;
+        }
+    }
+    if manifest
+        .path
+        .iter()
+        .any(|path| path.entry.trim().is_empty())
+    {
+        return Err("基线中的 PATH 条目不能为空".to_string());
+    }
+
+    Ok(manifest)
+}
+
+/// 检查当前环境是否符合基线，同时给出修复所需的修改（同一变量只修改一次）。
+/// 变量的修复值不符合其 pattern 时返回错误，避免修复后仍不符合基线
+pub fn evaluate(
+    snapshot: &EnvSnapshot,
+    manifest: &BaselineManifest,
+    file_path: &str,
+) -> Result<(BaselineReport, Vec<EnvChange>), String> {
+    let flavor = snapshot.flavor;
+    let mut expander = Expander::new(snapshot);
+    let mut results = Vec::new();
+    let mut changes: Vec<EnvChange> = Vec::new();
+
+    for required in &manifest.variables {
+        let (raw, expanded) = match required.scope {
+            Some(scope) => (
+                snapshot
+                    .get(scope, &required.name)
+                    .map(|var| var.value.clone()),
+                expander
+                    .expand_var(scope, &required.name)
+                    .map(|expansion| expansion.value),
+            ),
+            None => (
+                snapshot
+                    .get(EnvScope::User, &required.name)
+                    .or_else(|| snapshot.get(EnvScope::System, &required.name))
+                    .map(|var| var.value.clone()),
+                expander.effective_value(&required.name),
+            ),
+        };
+
+        // pattern 在加载时已校验
+        let pattern = required
+            .pattern
+            .as_deref()
+            .and_then(|pattern| Regex::new(pattern).ok());
+        let status = match (raw, expanded) {
+            (Some(raw), expanded) => {
+                let matches = pattern.as_ref().is_none_or(|pattern| {
+                    pattern.is_match(&raw)
+                        || expanded
+                            .as_deref()
+                            .is_some_and(|value| pattern.is_match(value))
+                });
+                if matches {
+                    RequirementStatus::Met
+                } else {
+                    RequirementStatus::Mismatch {
+                        actual: expanded.unwrap_or(raw),
+                    }
+                }
+            }
+            (None, _) => RequirementStatus::Missing,
+        };
+
+        let scope = required.scope.unwrap_or(EnvScope::User);
+        if let (Some(pattern), Some(value)) = (&pattern, &required.value) {
+            if !pattern.is_match(value)
+                && !pattern.is_match(&expander.expand_str(value, scope).value)
+            {
+                return Err(format!(
+                    "基线中变量 {} 的修复值 {} 不符合要求的格式 {}",
+                    required.name,
+                    value,
+                    required.pattern.as_deref().unwrap_or_default()
+                ));
+            }
+        }
+
+        let fix = match (&status, &required.value) {
+            (RequirementStatus::Met, _) | (_, None) => None,
+            (_, Some(value)) => {
+                push_change(
+                    &mut changes,
+                    flavor,
+                    EnvChange::Set {
+                        scope,
+                        var: EnvVar {
+                            name: snapshot
+                                .get(scope, &required.name)
+                                .map_or_else(|| required.name.clone(), |var| var.name.clone()),
+                            value: value.clone(),
+                            kind: required.kind.unwrap_or_else(|| EnvValueKind::infer(value)),
+                        },
+                    },
+                );
+                Some(format!(
+                    "设置{}变量 {} = {}",
+                    scope.label(),
+                    required.name,
+                    value
+                ))
+            }
+        };
+
+        results.push(RequirementResult {
+            target: RequirementTarget::Variable {
+                name: required.name.clone(),
+            },
+            scope: required.scope,
+            description: required.description.clone(),
+            status,
+            fix,
+        });
+    }
+
+    // 每个作用域缺少的 PATH 条目合并成一次写入
+    let mut missing_entries: Vec<(EnvScope, &str)> = Vec::new();
+    for required in &manifest.path {
+        let entries: Vec<String> = match required.scope {
+            Some(scope) => {
+                let raw = snapshot
+                    .get(scope, flavor.path_var_name())
+                    .map(|var| var.value.clone())
+                    .unwrap_or_default();
+                let expanded = expander
+                    .expand_var(scope, flavor.path_var_name())
+                    .map(|expansion| expansion.value)
+                    .unwrap_or_default();
+                split_path_list(&raw, flavor)
+                    .into_iter()
+                    .chain(split_path_list(&expanded, flavor))
+                    .collect()
+            }
+            None => expander
+                .effective_value(flavor.path_var_name())
+                .map(|value| split_path_list(&value, flavor))
+                .unwrap_or_default(),
+        };
+        let entries: Vec<String> = entries
+            .iter()
+            .map(|entry| normalize_path(entry, flavor))
+            .collect();
+
+        let scope = required.scope.unwrap_or(EnvScope::User);
+        let wanted = [
+            normalize_path(&required.entry, flavor),
+            normalize_path(&expander.expand_str(&required.entry, scope).value, flavor),
+        ];
+        let present = wanted.iter().any(|entry| entries.contains(entry));
+
+        let fix = (!present).then(|| {
+            missing_entries.push((scope, &required.entry));
+            format!("在{} PATH 末尾添加 {}", scope.label(), required.entry)
+        });
+        results.push(RequirementResult {
+            target: RequirementTarget::PathEntry {
+                entry: required.entry.clone(),
+            },
+            scope: required.scope,
+            description: required.description.clone(),
+            status: if present {
+                RequirementStatus::Met
+            } else {
+                RequirementStatus::Missing
+            },
+            fix,
+        });
+    }
+
+    for scope in [EnvScope::System, EnvScope::User] {
+        let added: Vec<String> = missing_entries
+            .iter()
+            .filter(|(entry_scope, _)| *entry_scope == scope)
+            .map(|(_, entry)| entry.to_string())
+            .collect();
+        if added.is_empty() {
+            continue;
+        }
+
+        // 变量要求中也设置了 PATH 时在其修复值的基础上追加
+        let existing = pending_value(&changes, flavor, scope, flavor.path_var_name())
+            .or_else(|| snapshot.get(scope, flavor.path_var_name()));
+        let mut entries = existing
+            .map(|var| split_path_list(&var.value, flavor))
+            .unwrap_or_default();
+        entries.extend(added);
+        let value = join_path_list(&entries, flavor);
+
+        // 新条目引用了其他变量时需要可展开类型才能生效
+        let kind = match existing.map(|var| var.kind) {
+            Some(EnvValueKind::ExpandString) => EnvValueKind::ExpandString,
+            _ if !expand::references(&value, flavor).is_empty() => EnvValueKind::ExpandString,
+            kind => kind.unwrap_or(EnvValueKind::String),
+        };
+        let name = existing.map_or_else(
+            || flavor.path_var_name().to_string(),
+            |var| var.name.clone(),
+        );
+        push_change(
+            &mut changes,
+            flavor,
+            EnvChange::Set {
+                scope,
+                var: EnvVar { name, value, kind },
+            },
+        );
+    }
+
+    let met = results
+        .iter()
+        .filter(|result| matches!(result.status, RequirementStatus::Met))
+        .count();
+    let report = BaselineReport {
+        name: manifest.name.clone(),
+        file_path: file_path.to_string(),
+        met,
+        violated: results.len() - met,
+        fixable: results.iter().filter(|result| result.fix.is_some()).count(),
+        results,
+    };
+
+    Ok((report, changes))
+}
+
+// 待写入的修改中某个变量的新值
+fn pending_value<'a>(
+    changes: &'a [EnvChange],
+    flavor: EnvFlavor,
+    scope: EnvScope,
+    name: &str,
+) -> Option<&'a EnvVar> {
+    changes.iter().find_map(|change| match change {
+        EnvChange::Set { scope: s, var } if *s == scope && flavor.names_equal(&var.name, name) => {
+            Some(var)
+        }
+        _ => None,
+    })
+}
+
+// 同一作用域的同一变量只保留最后一次修改，位置不变
+fn push_change(changes: &mut Vec<EnvChange>, flavor: EnvFlavor, change: EnvChange) {
+    let existing = changes.iter_mut().find(|existing| {
+        existing.scope() == change.scope() && flavor.names_equal(existing.name(), change.name())
+    });
+    match existing {
+        Some(existing) => *existing = change,
+        None => changes.push(change),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str, value: &str) -> EnvVar {
+        EnvVar {
+            name: name.to_string(),
+            value: value.to_string(),
+            kind: EnvValueKind::infer(value),
+        }
+    }
+
+    fn snapshot(system_vars: Vec<EnvVar>, user_vars: Vec<EnvVar>) -> EnvSnapshot {
+        EnvSnapshot {
+            flavor: EnvFlavor::Windows,
+            system_vars,
+            user_vars,
+        }
+    }
+
+    fn manifest(json: serde_json::Value) -> BaselineManifest {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn set_value(changes: &[EnvChange], scope: EnvScope) -> Option<&EnvVar> {
+        changes.iter().find_map(|change| match change {
+            EnvChange::Set { scope: s, var } if *s == scope => Some(var),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn loads_toml_and_json_manifests() {
+        let dir = tempfile::tempdir().unwrap();
+        let toml_path = dir.path().join("baseline.TOML");
+        fs::write(
+            &toml_path,
+            r#"
+name = "backend"
+
+[[variables]]
+name = "JAVA_HOME"
+pattern = 'jdk-?17'
+value = 'C:\jdk17'
+
+[[path]]
+entry = '%JAVA_HOME%\bin'
+scope = "user"
+"#,
+        )
+        .unwrap();
+        let manifest = load_manifest(&toml_path.to_string_lossy()).unwrap();
+        assert_eq!(manifest.name.as_deref(), Some("backend"));
+        assert_eq!(manifest.variables[0].value.as_deref(), Some("C:\\jdk17"));
+        assert_eq!(manifest.path[0].scope, Some(EnvScope::User));
+
+        let json_path = dir.path().join("baseline.json");
+        fs::write(
+            &json_path,
+            r#"{"variables":[{"name":"GOPATH","scope":"system"}]}"#,
+        )
+        .unwrap();
+        let manifest = load_manifest(&json_path.to_string_lossy()).unwrap();
+        assert_eq!(manifest.name, None);
+        assert_eq!(manifest.variables[0].scope, Some(EnvScope::System));
+        assert!(manifest.path.is_empty());
+
+        fs::write(&json_path, r#"{"variables":[{"name":"A","pattern":"("}]}"#).unwrap();
+        let error = load_manifest(&json_path.to_string_lossy()).unwrap_err();
+        assert!(error.starts_with("变量 A 的正则表达式错误"));
+
+        fs::write(&json_path, r#"{"path":[{"entry":"  "}]}"#).unwrap();
+        assert_eq!(
+            load_manifest(&json_path.to_string_lossy()).unwrap_err(),
+            "基线中的 PATH 条目不能为空"
+        );
+    }
+
+    #[test]
+    fn patterns_match_raw_or_expanded_values() {
+        let snapshot = snapshot(
+            vec![var("JDK_ROOT", "C:\\jdks")],
+            vec![
+                var("JAVA_HOME", "%JDK_ROOT%\\17"),
+                var("MAVEN_HOME", "C:\\maven3"),
+            ],
+        );
+        let manifest = manifest(serde_json::json!({
+            "variables": [
+                { "name": "JAVA_HOME", "pattern": "^C:\\\\jdks\\\\" },
+                { "name": "java_home", "scope": "user", "pattern": "^%JDK_ROOT%" },
+                { "name": "JAVA_HOME", "pattern": "jdk8" },
+                { "name": "MAVEN_HOME", "pattern": "maven4", "value": "C:\\maven4" },
+                { "name": "GRADLE_HOME" },
+            ],
+        }));
+
+        let (report, changes) = evaluate(&snapshot, &manifest, "baseline.json").unwrap();
+        let statuses: Vec<&RequirementStatus> =
+            report.results.iter().map(|result| &result.status).collect();
+        assert!(matches!(statuses[0], RequirementStatus::Met));
+        assert!(matches!(statuses[1], RequirementStatus::Met));
+        assert!(
+            matches!(statuses[2], RequirementStatus::Mismatch { actual } if actual == "C:\\jdks\\17")
+        );
+        assert!(matches!(statuses[3], RequirementStatus::Mismatch { .. }));
+        assert!(matches!(statuses[4], RequirementStatus::Missing));
+        assert_eq!((report.met, report.violated, report.fixable), (2, 3, 1));
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            set_value(&changes, EnvScope::User),
+            Some(&var("MAVEN_HOME", "C:\\maven4"))
+        );
+    }
+
+    #[test]
+    fn merges_missing_path_entries_into_one_write_per_scope() {
+        let snapshot = snapshot(
+            vec![var("Path", "C:\\sys")],
+            vec![var("Path", "C:\\a"), var("TOOLS", "C:\\tools")],
+        );
+        let manifest = manifest(serde_json::json!({
+            "variables": [
+                { "name": "Path", "scope": "user", "pattern": "x", "value": "C:\\a;C:\\x" },
+            ],
+            "path": [
+                { "entry": "C:\\b" },
+                { "entry": "c:\\A\\" },
+                { "entry": "%TOOLS%\\bin", "scope": "user" },
+                { "entry": "C:\\sys2", "scope": "system" },
+            ],
+        }));
+
+        let (report, changes) = evaluate(&snapshot, &manifest, "baseline.json").unwrap();
+        assert_eq!(report.fixable, 4);
+        assert!(report.results[2].fix.is_none());
+
+        // 用户 PATH 在变量要求的修复值基础上追加，只写一次
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            set_value(&changes, EnvScope::User),
+            Some(&EnvVar {
+                name: "Path".to_string(),
+                value: "C:\\a;C:\\x;C:\\b;%TOOLS%\\bin".to_string(),
+                kind: EnvValueKind::ExpandString,
+            })
+        );
+        assert_eq!(
+            set_value(&changes, EnvScope::System),
+            Some(&var("Path", "C:\\sys;C:\\sys2"))
+        );
+    }
+
+    #[test]
+    fn rejects_fix_values_that_do_not_match_pattern() {
+        let snapshot = snapshot(Vec::new(), vec![var("JDK_ROOT", "C:\\jdk17")]);
+        let manifest = manifest(serde_json::json!({
+            "variables": [
+                { "name": "JAVA_HOME", "pattern": "17$", "value": "%JDK_ROOT%" },
+                { "name": "GOPATH", "pattern": "^C:", "value": "D:\\go" },
+            ],
+        }));
+
+        let error = evaluate(&snapshot, &manifest, "baseline.json").unwrap_err();
+        assert_eq!(
+            error,
+            "基线中变量 GOPATH 的修复值 D:\\go 不符合要求的格式 ^C:"
+        );
+    }
+}
@@ -0,0 +1,299 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+
+use super::expand::{references, resolve_reference, Expander};
+use super::path_analysis::split_path_list;
+use super::store::{EnvFlavor, EnvScope, EnvSnapshot};
+use super::EnvValueKind;
+
+/// 变量之间的一条引用关系，to_scope 为 None 表示引用无法解析（未定义或来自进程环境）
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyEdge {
+    pub from_scope: EnvScope,
+    pub from: String,
+    pub to_scope: Option<EnvScope>,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyNode {
+    pub scope: EnvScope,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyGraph {
+    /// 引用了其他变量或被其他变量引用的变量
+    pub nodes: Vec<DependencyNode>,
+    pub edges: Vec<DependencyEdge>,
+}
+
+/// 依赖被删除变量的变量
+#[derive(Debug, Clone, Serialize)]
+pub struct DependentVar {
+    pub scope: EnvScope,
+    pub name: String,
+    /// 间接依赖时经过的变量，直接引用时为空
+    pub via: Vec<String>,
+    pub value_before: String,
+    pub value_after: String,
+    /// 删除后引用是否无法解析（另一作用域有同名变量时仍可解析，只是值会变化）
+    pub broken: bool,
+}
+
+/// 引用了被删除变量的 PATH 条目
+#[derive(Debug, Clone, Serialize)]
+pub struct PathEntryImpact {
+    pub scope: EnvScope,
+    pub index: usize,
+    pub entry: String,
+    pub value_before: String,
+    pub value_after: String,
+    pub broken: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImpactReport {
+    pub scope: EnvScope,
+    pub name: String,
+    pub exists: bool,
+    /// 不包括 PATH 本身，PATH 的影响按条目列在 path_entries 中
+    pub dependents: Vec<DependentVar>,
+    pub path_entries: Vec<PathEntryImpact>,
+    /// 删除后会无法解析的变量和 PATH 条目数量
+    pub broken: usize,
+}
+
+/// 按与展开相同的解析规则构建变量引用图，只有可展开类型的变量会产生引用
+pub fn build_graph(snapshot: &EnvSnapshot) -> DependencyGraph {
+    let flavor = snapshot.flavor;
+    let mut edges = Vec::new();
+
+    for scope in [EnvScope::System, EnvScope::User] {
+        for var in snapshot.vars(scope) {
+            if var.kind != EnvValueKind::ExpandString {
+                continue;
+            }
+            for name in references(&var.value, flavor) {
+                let target = resolve_reference(snapshot, scope, Some(&var.name), name);
+                let duplicate = edges.iter().any(|edge: &DependencyEdge| {
+                    edge.from_scope == scope
+                        && flavor.names_equal(&edge.from, &var.name)
+                        && flavor.names_equal(&edge.to, name)
+                });
+                if duplicate {
+                    continue;
+                }
+                edges.push(DependencyEdge {
+                    from_scope: scope,
+                    from: var.name.clone(),
+                    to_scope: target.map(|(scope, _)| scope),
+                    to: target.map_or_else(|| name.to_string(), |(_, var)| var.name.clone()),
+                });
+            }
+        }
+    }
+
+    let mut nodes: Vec<DependencyNode> = Vec::new();
+    let endpoints = edges.iter().flat_map(|edge| {
+        [
+            Some((edge.from_scope, &edge.from)),
+            edge.to_scope.map(|scope| (scope, &edge.to)),
+        ]
+    });
+    for (scope, name) in endpoints.flatten() {
+        if !nodes
+            .iter()
+            .any(|node| node.scope == scope && flavor.names_equal(&node.name, name))
+        {
+            nodes.push(DependencyNode {
+                scope,
+                name: name.clone(),
+            });
+        }
+    }
+
+    DependencyGraph { nodes, edges }
+}
+
+/// 分析删除（或重命名）某个变量后受影响的变量和 PATH 条目，包括间接依赖
+pub fn analyze_impact(snapshot: &EnvSnapshot, scope: EnvScope, name: &str) -> ImpactReport {
+    let flavor = snapshot.flavor;
+    let graph = build_graph(snapshot);
+    let is_node = |node: &(EnvScope, String), scope: EnvScope, name: &str| {
+        node.0 == scope && flavor.names_equal(&node.1, name)
+    };
+
+    // 沿反向边做广度优先搜索，parents[i] 为 visited[i] 所依赖的上一个节点
+    let mut visited: Vec<(EnvScope, String)> = vec![(scope, name.to_string())];
+    let mut parents: Vec<Option<usize>> = vec![None];
+    let mut queue = VecDeque::from([0]);
+    while let Some(current) = queue.pop_front() {
+        let (current_scope, current_name) = visited[current].clone();
+        for edge in &graph.edges {
+            if edge.to_scope != Some(current_scope) || !flavor.names_equal(&edge.to, &current_name)
+            {
+                continue;
+            }
+            if visited
+                .iter()
+                .any(|node| is_node(node, edge.from_scope, &edge.from))
+            {
+                continue;
+            }
+            visited.push((edge.from_scope, edge.from.clone()));
+            parents.push(Some(current));
+            queue.push_back(visited.len() - 1);
+        }
+    }
+
+    let mut after = snapshot.clone();
+    match scope {
+        EnvScope::System => after
+            .system_vars
+            .retain(|var| !flavor.names_equal(&var.name, name)),
+        EnvScope::User => after
+            .user_vars
+            .retain(|var| !flavor.names_equal(&var.name, name)),
+    }
+    // 被删除的变量即使在当前进程环境中存在，删除后新启动的程序也看不到，不能用它补上
+    let mut expander_before = Expander::new(snapshot).without_process_env();
+    let mut expander_after = Expander::new(&after).without_process_env();
+    let path_name = flavor.path_var_name();
+
+    let mut dependents = Vec::new();
+    for (index, (dep_scope, dep_name)) in visited.iter().enumerate().skip(1) {
+        if flavor.names_equal(dep_name, path_name) {
+            continue;
+        }
+
+        let mut via = Vec::new();
+        let mut parent = parents[index];
+        while let Some(parent_index) = parent.filter(|&parent_index| parent_index != 0) {
+            via.push(visited[parent_index].1.clone());
+            parent = parents[parent_index];
+        }
+
+        let before = expander_before.expand_var(*dep_scope, dep_name);
+        let after = expander_after.expand_var(*dep_scope, dep_name);
+        dependents.push(DependentVar {
+            scope: *dep_scope,
+            name: dep_name.clone(),
+            via,
+            broken: after
+                .as_ref()
+                .is_some_and(|after| contains_name(flavor, &after.undefined, name)),
+            value_before: before.map(|expansion| expansion.value).unwrap_or_default(),
+            value_after: after.map(|expansion| expansion.value).unwrap_or_default(),
+        });
+    }
+
+    // 条目中直接引用了被删除变量或任一受影响变量的 PATH 条目
+    let mut path_entries = Vec::new();
+    for path_scope in [EnvScope::System, EnvScope::User] {
+        let Some(path_var) = snapshot.get(path_scope, path_name) else {
+            continue;
+        };
+        if path_var.kind != EnvValueKind::ExpandString {
+            continue;
+        }
+
+        for (index, entry) in split_path_list(&path_var.value, flavor)
+            .into_iter()
+            .enumerate()
+        {
+            // 用户 PATH 中的 %PATH% 指向系统 PATH，其条目已单独列出
+            let touches = references(&entry, flavor).into_iter().any(|reference| {
+                !flavor.names_equal(reference, path_name)
+                    && resolve_reference(snapshot, path_scope, Some(path_name), reference)
+                        .is_some_and(|(target_scope, target)| {
+                            visited
+                                .iter()
+                                .any(|node| is_node(node, target_scope, &target.name))
+                        })
+            });
+            if !touches {
+                continue;
+            }
+
+            let after = expander_after.expand_str(&entry, path_scope);
+            path_entries.push(PathEntryImpact {
+                scope: path_scope,
+                index,
+                value_before: expander_before.expand_str(&entry, path_scope).value,
+                broken: contains_name(flavor, &after.undefined, name),
+                value_after: after.value,
+                entry,
+            });
+        }
+    }
+
+    let broken = dependents.iter().filter(|dep| dep.broken).count()
+        + path_entries.iter().filter(|entry| entry.broken).count();
+    ImpactReport {
+        scope,
+        name: snapshot
+            .get(scope, name)
+            .map_or_else(|| name.to_string(), |var| var.name.clone()),
+        exists: snapshot.get(scope, name).is_some(),
+        dependents,
+        path_entries,
+        broken,
+    }
+}
+
+fn contains_name(flavor: EnvFlavor, names: &[String], name: &str) -> bool {
+    names.iter().any(|other| flavor.names_equal(other, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env_var::EnvVar;
+
+    fn var(name: &str, value: &str) -> EnvVar {
+        EnvVar {
+            name: name.to_string(),
+            value: value.to_string(),
+            kind: EnvValueKind::ExpandString,
+        }
+    }
+
+    #[test]
+    fn deleted_var_is_broken_even_if_set_in_process_env() {
+        // HOME 是动态变量，普通展开时会从进程环境获取
+        if std::env::var_os("HOME").is_none() {
+            std::env::set_var("HOME", "/home/process");
+        }
+        let snapshot = EnvSnapshot {
+            flavor: EnvFlavor::Posix,
+            system_vars: vec![var("HOME", "/home/me")],
+            user_vars: vec![
+                var("M2_HOME", "$HOME/.m2"),
+                var("M2_BIN", "${M2_HOME}/bin"),
+                var("PATH", "/usr/bin:$M2_BIN"),
+            ],
+        };
+
+        let report = analyze_impact(&snapshot, EnvScope::System, "HOME");
+        assert!(report.exists);
+        let dependents: Vec<(&str, bool, &str)> = report
+            .dependents
+            .iter()
+            .map(|dep| (dep.name.as_str(), dep.broken, dep.value_after.as_str()))
+            .collect();
+        assert_eq!(
+            dependents,
+            [
+                ("M2_HOME", true, "$HOME/.m2"),
+                ("M2_BIN", true, "$HOME/.m2/bin")
+            ]
+        );
+        assert_eq!(report.dependents[1].via, ["M2_HOME"]);
+
+        assert_eq!(report.path_entries.len(), 1);
+        assert_eq!(report.path_entries[0].value_before, "/home/me/.m2/bin");
+        assert!(report.path_entries[0].broken);
+        assert_eq!(report.broken, 3);
+    }
+}
@@ -0,0 +1,342 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::import::DiffKind;
+use super::path_analysis::{normalize_path, split_path_list};
+use super::secrets::{self, SecretHandling, REDACTED_VALUE};
+use super::stale::{classify_value, ValueClass};
+use super::store::{EnvFlavor, EnvScope};
+use super::{EnvValueKind, EnvVar, EnvVarExport};
+
+/// 对比的一侧：备份文件或当前环境
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffSource {
+    /// 文件路径，当前环境为 None
+    pub file_path: Option<String>,
+    pub export_time: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PathEntryChange {
+    pub index: usize,
+    pub entry: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MovedPathEntry {
+    pub entry: String,
+    pub from: usize,
+    pub to: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RewrittenPathEntry {
+    pub old: String,
+    pub new: String,
+    pub from: usize,
+    pub to: usize,
+}
+
+/// 路径列表逐条对比的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct PathListDiff {
+    pub added: Vec<PathEntryChange>,
+    pub removed: Vec<PathEntryChange>,
+    /// 两侧都有但相对顺序发生变化的条目
+    pub moved: Vec<MovedPathEntry>,
+    /// 条目本身相同、只是写法不同（大小写、结尾分隔符等）
+    pub rewritten: Vec<RewrittenPathEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VarChange {
+    pub name: String,
+    pub change: DiffKind,
+    pub old_value: Option<String>,
+    pub old_kind: Option<EnvValueKind>,
+    pub new_value: Option<String>,
+    pub new_kind: Option<EnvValueKind>,
+    /// 路径列表类变量的逐条对比
+    pub path_diff: Option<PathListDiff>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScopeDiff {
+    pub scope: EnvScope,
+    /// 只包含有变化的变量
+    pub changes: Vec<VarChange>,
+    pub added: usize,
+    pub changed: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvDiffReport {
+    pub base: DiffSource,
+    pub target: DiffSource,
+    pub system: ScopeDiff,
+    pub user: ScopeDiff,
+    /// 敏感变量的处理方式
+    pub secrets: SecretHandling,
+    /// 因涉及敏感值而未列出的变化数量
+    pub hidden: usize,
+}
+
+pub fn diff_exports(
+    flavor: EnvFlavor,
+    base: (&EnvVarExport, DiffSource),
+    target: (&EnvVarExport, DiffSource),
+    secrets: SecretHandling,
+) -> EnvDiffReport {
+    let mut hidden = 0;
+    EnvDiffReport {
+        system: diff_scope(
+            flavor,
+            EnvScope::System,
+            &base.0.system_vars,
+            &target.0.system_vars,
+            secrets,
+            &mut hidden,
+        ),
+        user: diff_scope(
+            flavor,
+            EnvScope::User,
+            &base.0.user_vars,
+            &target.0.user_vars,
+            secrets,
+            &mut hidden,
+        ),
+        base: base.1,
+        target: target.1,
+        secrets,
+        hidden,
+    }
+}
+
+fn diff_scope(
+    flavor: EnvFlavor,
+    scope: EnvScope,
+    base: &[EnvVar],
+    target: &[EnvVar],
+    secrets: SecretHandling,
+    hidden: &mut usize,
+) -> ScopeDiff {
+    let mut changes = Vec::new();
+    let mut unchanged = 0;
+
+    for old in base {
+        match target
+            .iter()
+            .find(|var| flavor.names_equal(&var.name, &old.name))
+        {
+            Some(new) if new.value == old.value && new.kind == old.kind => unchanged += 1,
+            Some(new) => changes.push(VarChange {
+                name: old.name.clone(),
+                change: DiffKind::Changed,
+                old_value: Some(old.value.clone()),
+                old_kind: Some(old.kind),
+                new_value: Some(new.value.clone()),
+                new_kind: Some(new.kind),
+                path_diff: is_path_list(flavor, old, new)
+                    .then(|| diff_path_list(flavor, &old.value, &new.value)),
+            }),
+            None => changes.push(VarChange {
+                name: old.name.clone(),
+                change: DiffKind::Removed,
+                old_value: Some(old.value.clone()),
+                old_kind: Some(old.kind),
+                new_value: None,
+                new_kind: None,
+                path_diff: None,
+            }),
+        }
+    }
+
+    for new in target {
+        if !base
+            .iter()
+            .any(|var| flavor.names_equal(&var.name, &new.name))
+        {
+            changes.push(VarChange {
+                name: new.name.clone(),
+                change: DiffKind::Added,
+                old_value: None,
+                old_kind: None,
+                new_value: Some(new.value.clone()),
+                new_kind: Some(new.kind),
+                path_diff: None,
+            });
+        }
+    }
+
+    if secrets != SecretHandling::Include {
+        *hidden += mask_changes(flavor, secrets, &mut changes);
+    }
+
+    let count = |kind: DiffKind| {
+        changes
+            .iter()
+            .filter(|change| change.change == kind)
+            .count()
+    };
+    ScopeDiff {
+        scope,
+        added: count(DiffKind::Added),
+        changed: count(DiffKind::Changed),
+        removed: count(DiffKind::Removed),
+        unchanged,
+        changes,
+    }
+}
+
+// 任一侧的值为敏感值时隐藏两侧的值，或直接不列出该变化；返回不列出的数量
+fn mask_changes(
+    flavor: EnvFlavor,
+    handling: SecretHandling,
+    changes: &mut Vec<VarChange>,
+) -> usize {
+    let is_secret = |change: &VarChange, value: &Option<String>, kind: Option<EnvValueKind>| {
+        value.as_ref().zip(kind).is_some_and(|(value, kind)| {
+            let var = EnvVar {
+                name: change.name.clone(),
+                value: value.clone(),
+                kind,
+            };
+            secrets::detect(flavor, &var).is_some()
+        })
+    };
+
+    let count = changes.len();
+    changes.retain_mut(|change| {
+        let secret = is_secret(change, &change.old_value, change.old_kind)
+            || is_secret(change, &change.new_value, change.new_kind);
+        if !secret {
+            return true;
+        }
+        if handling == SecretHandling::Omit {
+            return false;
+        }
+        for value in [&mut change.old_value, &mut change.new_value]
+            .into_iter()
+            .flatten()
+        {
+            *value = REDACTED_VALUE.to_string();
+        }
+        change.path_diff = None;
+        true
+    });
+    count - changes.len()
+}
+
+// PATH 本身以及任一侧的值形如路径列表的变量
+fn is_path_list(flavor: EnvFlavor, old: &EnvVar, new: &EnvVar) -> bool {
+    flavor.names_equal(&old.name, flavor.path_var_name())
+        || classify_value(&old.value, flavor) == ValueClass::PathList
+        || classify_value(&new.value, flavor) == ValueClass::PathList
+}
+
+/// 逐条对比两个路径列表，按规范化后的路径匹配条目
+pub fn diff_path_list(flavor: EnvFlavor, old: &str, new: &str) -> PathListDiff {
+    let old_entries = split_path_list(old, flavor);
+    let new_entries = split_path_list(new, flavor);
+    let old_keys: Vec<String> = old_entries
+        .iter()
+        .map(|e| normalize_path(e, flavor))
+        .collect();
+    let new_keys: Vec<String> = new_entries
+        .iter()
+        .map(|e| normalize_path(e, flavor))
+        .collect();
+
+    // 重复的条目按出现次序一一匹配
+    let mut new_positions: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, key) in new_keys.iter().enumerate().rev() {
+        new_positions.entry(key).or_default().push(index);
+    }
+
+    let mut matched: Vec<(usize, usize)> = Vec::new();
+    let mut removed = Vec::new();
+    for (index, key) in old_keys.iter().enumerate() {
+        match new_positions
+            .get_mut(key.as_str())
+            .and_then(|positions| positions.pop())
+        {
+            Some(new_index) => matched.push((index, new_index)),
+            None => removed.push(PathEntryChange {
+                index,
+                entry: old_entries[index].clone(),
+            }),
+        }
+    }
+
+    let matched_new: Vec<bool> = {
+        let mut flags = vec![false; new_entries.len()];
+        for &(_, new_index) in &matched {
+            flags[new_index] = true;
+        }
+        flags
+    };
+    let added = new_entries
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !matched_new[*index])
+        .map(|(index, entry)| PathEntryChange {
+            index,
+            entry: entry.clone(),
+        })
+        .collect();
+
+    // 保持相对顺序的最长子序列之外的匹配条目视为被移动
+    let stable = longest_increasing_subsequence(&matched);
+    let mut moved = Vec::new();
+    let mut rewritten = Vec::new();
+    for (position, &(from, to)) in matched.iter().enumerate() {
+        if !stable.contains(&position) {
+            moved.push(MovedPathEntry {
+                entry: new_entries[to].clone(),
+                from,
+                to,
+            });
+        }
+        if old_entries[from] != new_entries[to] {
+            rewritten.push(RewrittenPathEntry {
+                old: old_entries[from].clone(),
+                new: new_entries[to].clone(),
+                from,
+                to,
+            });
+        }
+    }
+
+    PathListDiff {
+        added,
+        removed,
+        moved,
+        rewritten,
+    }
+}
+
+// 返回 matched 中按新位置递增的最长子序列的下标（matched 已按旧位置排序）
+fn longest_increasing_subsequence(matched: &[(usize, usize)]) -> Vec<usize> {
+    let n = matched.len();
+    let mut lengths = vec![1usize; n];
+    let mut previous = vec![None; n];
+
+    for i in 0..n {
+        for j in 0..i {
+            if matched[j].1 < matched[i].1 && lengths[j] + 1 > lengths[i] {
+                lengths[i] = lengths[j] + 1;
+                previous[i] = Some(j);
+            }
+        }
+    }
+
+    let mut result = Vec::new();
+    let mut current = (0..n).max_by_key(|&i| lengths[i]);
+    while let Some(i) = current {
+        result.push(i);
+        current = previous[i];
+    }
+    result
+}
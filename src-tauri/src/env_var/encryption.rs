@@ -0,0 +1,324 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+
+use super::backup;
+use super::EnvVarExport;
+
+/// 加密备份文件的格式标识
+pub const ENVELOPE_FORMAT: &str = "my-tools.encrypted-env-backup";
+/// 当前写入的加密格式版本
+pub const ENVELOPE_VERSION: u32 = 1;
+
+const KDF_ALGORITHM: &str = "argon2id";
+const CIPHER_ALGORITHM: &str = "aes-256-gcm";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+// 新备份使用的 Argon2id 参数（19 MiB 内存、2 轮、单线程）
+const DEFAULT_MEMORY_KIB: u32 = 19 * 1024;
+const DEFAULT_ITERATIONS: u32 = 2;
+const DEFAULT_PARALLELISM: u32 = 1;
+// 读取时允许的上限，避免构造的文件让解密占用过多内存或时间
+const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_ITERATIONS: u32 = 64;
+const MAX_PARALLELISM: u32 = 16;
+
+/// 无需密码即可读取的备份信息，列出备份时使用。
+/// 只包含时间、版本和数量，主机名、用户名等来源信息只保存在密文中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupMetadata {
+    pub export_time: String,
+    /// 导出时的应用版本
+    pub version: String,
+    pub system_vars_count: usize,
+    pub user_vars_count: usize,
+    /// 加密内容的结构版本，早期的加密备份没有该字段（均为结构版本 1）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub algorithm: String,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    /// Base64 编码
+    pub salt: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CipherParams {
+    pub algorithm: String,
+    /// Base64 编码
+    pub nonce: String,
+}
+
+/// 加密后的备份文件。metadata 以明文保存，它在文件中的原始 JSON 与格式版本一起作为附加数据
+/// 参与认证，被篡改时无法解密
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedBackup {
+    pub format: String,
+    pub version: u32,
+    /// BackupMetadata 的 JSON，按原样读写，避免重新序列化后与认证的内容不一致
+    pub metadata: Box<RawValue>,
+    pub kdf: KdfParams,
+    pub cipher: CipherParams,
+    /// Base64 编码的密文（包含认证标签）
+    pub ciphertext: String,
+}
+
+impl EncryptedBackup {
+    /// 解析明文元数据（未经认证，仅用于展示）
+    pub fn metadata(&self) -> Result<BackupMetadata, String> {
+        serde_json::from_str(self.metadata.get())
+            .map_err(|e| format!("加密备份文件已损坏（metadata）: {}", e))
+    }
+}
+
+// 只读取格式标识，用于判断文件是否为加密备份
+#[derive(Deserialize)]
+struct EnvelopeHeader {
+    format: String,
+    version: u32,
+}
+
+/// 文件不是加密备份时返回 Ok(None)，是加密备份但版本不支持或内容损坏时返回错误
+pub fn parse_envelope(bytes: &[u8]) -> Result<Option<EncryptedBackup>, String> {
+    let Ok(header) = serde_json::from_slice::<EnvelopeHeader>(bytes) else {
+        return Ok(None);
+    };
+    if header.format != ENVELOPE_FORMAT {
+        return Ok(None);
+    }
+    if header.version != ENVELOPE_VERSION {
+        return Err(format!(
+            "不支持的加密备份版本: {}，请升级到最新版本后再试",
+            header.version
+        ));
+    }
+
+    let envelope: EncryptedBackup =
+        serde_json::from_slice(bytes).map_err(|e| format!("加密备份文件已损坏: {}", e))?;
+    envelope.metadata()?;
+    Ok(Some(envelope))
+}
+
+/// 用密码加密导出数据，加密内容与未加密的备份文件相同（包含校验和）
+pub fn encrypt(data: &EnvVarExport, passphrase: &str) -> Result<EncryptedBackup, String> {
+    check_passphrase(passphrase)?;
+
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let metadata = BackupMetadata {
+        export_time: data.export_info.export_time.clone(),
+        version: data.export_info.app_version.clone().unwrap_or_default(),
+        system_vars_count: data.system_vars.len(),
+        user_vars_count: data.user_vars.len(),
+        schema_version: Some(data.export_info.schema_version),
+    };
+    let metadata = serde_json::to_string(&metadata)
+        .and_then(RawValue::from_string)
+        .map_err(|e| format!("序列化数据失败: {}", e))?;
+
+    let mut envelope = EncryptedBackup {
+        format: ENVELOPE_FORMAT.to_string(),
+        version: ENVELOPE_VERSION,
+        metadata,
+        kdf: KdfParams {
+            algorithm: KDF_ALGORITHM.to_string(),
+            memory_kib: DEFAULT_MEMORY_KIB,
+            iterations: DEFAULT_ITERATIONS,
+            parallelism: DEFAULT_PARALLELISM,
+            salt: STANDARD.encode(salt),
+        },
+        cipher: CipherParams {
+            algorithm: CIPHER_ALGORITHM.to_string(),
+            nonce: STANDARD.encode(nonce),
+        },
+        ciphertext: String::new(),
+    };
+
+    let plaintext = backup::to_json(data)?;
+    let cipher = Aes256Gcm::new_from_slice(&derive_key(&envelope.kdf, passphrase, &salt)?)
+        .map_err(|e| format!("初始化加密失败: {}", e))?;
+    let aad = associated_data(&envelope);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext.as_bytes(),
+                aad: &aad,
+            },
+        )
+        .map_err(|_| "加密失败".to_string())?;
+
+    envelope.ciphertext = STANDARD.encode(ciphertext);
+    Ok(envelope)
+}
+
+/// 用密码解密出备份文件内容，密码错误和文件被篡改都会返回错误
+pub fn decrypt(envelope: &EncryptedBackup, passphrase: &str) -> Result<Vec<u8>, String> {
+    check_passphrase(passphrase)?;
+    if envelope.kdf.algorithm != KDF_ALGORITHM {
+        return Err(format!("不支持的密钥派生算法: {}", envelope.kdf.algorithm));
+    }
+    if envelope.cipher.algorithm != CIPHER_ALGORITHM {
+        return Err(format!("不支持的加密算法: {}", envelope.cipher.algorithm));
+    }
+    if envelope.kdf.memory_kib > MAX_MEMORY_KIB
+        || envelope.kdf.iterations > MAX_ITERATIONS
+        || envelope.kdf.parallelism > MAX_PARALLELISM
+    {
+        return Err("加密备份的密钥派生参数超出允许范围".to_string());
+    }
+
+    let decode = |field: &str, value: &str| {
+        STANDARD
+            .decode(value)
+            .map_err(|e| format!("加密备份文件已损坏（{}）: {}", field, e))
+    };
+    let salt = decode("salt", &envelope.kdf.salt)?;
+    let nonce = decode("nonce", &envelope.cipher.nonce)?;
+    let ciphertext = decode("ciphertext", &envelope.ciphertext)?;
+    if salt.len() < 8 || nonce.len() != NONCE_LEN {
+        return Err("加密备份文件已损坏".to_string());
+    }
+
+    let cipher = Aes256Gcm::new_from_slice(&derive_key(&envelope.kdf, passphrase, &salt)?)
+        .map_err(|e| format!("初始化解密失败: {}", e))?;
+    let aad = associated_data(envelope);
+    cipher
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| "密码错误或备份文件已被修改".to_string())
+}
+
+fn check_passphrase(passphrase: &str) -> Result<(), String> {
+    if passphrase.is_empty() {
+        return Err("密码不能为空".to_string());
+    }
+    Ok(())
+}
+
+fn derive_key(kdf: &KdfParams, passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|e| format!("密钥派生参数错误: {}", e))?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("密钥派生失败: {}", e))?;
+    Ok(key)
+}
+
+// 格式标识、版本和明文元数据在文件中的原始内容
+fn associated_data(envelope: &EncryptedBackup) -> Vec<u8> {
+    let mut aad = format!("{}\n{}\n", envelope.format, envelope.version).into_bytes();
+    aad.extend_from_slice(envelope.metadata.get().as_bytes());
+    aad
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env_var::{EnvValueKind, EnvVar};
+
+    fn export() -> EnvVarExport {
+        let var = |name: &str, value: &str| EnvVar {
+            name: name.to_string(),
+            value: value.to_string(),
+            kind: EnvValueKind::infer(value),
+        };
+        backup::new_export(
+            vec![var("ComSpec", "%SystemRoot%\\system32\\cmd.exe")],
+            vec![var("API_TOKEN", "s3cr3t"), var("JAVA_HOME", "C:\\jdk")],
+        )
+    }
+
+    // 按写入文件后再读取的方式得到加密备份
+    fn encrypted(passphrase: &str) -> String {
+        serde_json::to_string_pretty(&encrypt(&export(), passphrase).unwrap()).unwrap()
+    }
+
+    fn reparse(json: &str) -> EncryptedBackup {
+        parse_envelope(json.as_bytes()).unwrap().unwrap()
+    }
+
+    #[test]
+    fn round_trip_keeps_data_and_plain_metadata() {
+        let envelope = reparse(&encrypted("correct horse"));
+        let metadata = envelope.metadata().unwrap();
+        assert_eq!(
+            (metadata.system_vars_count, metadata.user_vars_count),
+            (1, 2)
+        );
+        assert_eq!(metadata.schema_version, Some(backup::SCHEMA_VERSION));
+        assert!(!envelope.ciphertext.contains("s3cr3t"));
+
+        let loaded = backup::parse_json(&decrypt(&envelope, "correct horse").unwrap()).unwrap();
+        assert_eq!(loaded.checksum, backup::ChecksumStatus::Verified);
+        assert_eq!(loaded.data.user_vars, export().user_vars);
+        assert_eq!(loaded.data.system_vars, export().system_vars);
+    }
+
+    #[test]
+    fn rejects_wrong_or_empty_passphrase() {
+        let envelope = reparse(&encrypted("correct horse"));
+        assert_eq!(
+            decrypt(&envelope, "wrong horse").unwrap_err(),
+            "密码错误或备份文件已被修改"
+        );
+        assert_eq!(decrypt(&envelope, "").unwrap_err(), "密码不能为空");
+    }
+
+    #[test]
+    fn rejects_tampered_ciphertext() {
+        let mut envelope = reparse(&encrypted("correct horse"));
+        let mut ciphertext = STANDARD.decode(&envelope.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        envelope.ciphertext = STANDARD.encode(ciphertext);
+
+        assert_eq!(
+            decrypt(&envelope, "correct horse").unwrap_err(),
+            "密码错误或备份文件已被修改"
+        );
+    }
+
+    #[test]
+    fn rejects_tampered_metadata() {
+        let json = encrypted("correct horse");
+        let tampered = json.replace("\"user_vars_count\":2", "\"user_vars_count\":3");
+        assert_ne!(tampered, json);
+
+        let envelope = reparse(&tampered);
+        assert_eq!(envelope.metadata().unwrap().user_vars_count, 3);
+        assert_eq!(
+            decrypt(&envelope, "correct horse").unwrap_err(),
+            "密码错误或备份文件已被修改"
+        );
+    }
+
+    #[test]
+    fn ignores_other_json_and_rejects_unknown_versions() {
+        assert!(parse_envelope(br#"{"export_info":{}}"#).unwrap().is_none());
+        assert!(parse_envelope(b"KEY=value").unwrap().is_none());
+
+        let json = encrypted("correct horse").replace("\"version\": 1,", "\"version\": 2,");
+        assert!(parse_envelope(json.as_bytes())
+            .unwrap_err()
+            .starts_with("不支持的加密备份版本: 2"));
+    }
+}
@@ -0,0 +1,616 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::store::{EnvFlavor, EnvScope, EnvSnapshot};
+use super::{EnvValueKind, EnvVar};
+
+/// 值中的一段：普通文本或变量引用
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    /// `name` 为变量名，`raw` 为原始写法（如 `%JAVA_HOME%`、`${HOME}`）
+    Ref {
+        name: &'a str,
+        raw: &'a str,
+    },
+}
+
+/// 按平台语法拆分值：Windows 为 `%NAME%`，POSIX 为 `$NAME` / `${NAME}`
+pub fn segments(value: &str, flavor: EnvFlavor) -> Vec<Segment<'_>> {
+    match flavor {
+        EnvFlavor::Windows => windows_segments(value),
+        EnvFlavor::Posix => posix_segments(value),
+    }
+}
+
+/// 值中直接引用的变量名
+pub fn references(value: &str, flavor: EnvFlavor) -> Vec<&str> {
+    segments(value, flavor)
+        .into_iter()
+        .filter_map(|segment| match segment {
+            Segment::Ref { name, .. } => Some(name),
+            Segment::Text(_) => None,
+        })
+        .collect()
+}
+
+fn is_windows_ref_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains([';', '\\', '/', ' '])
+}
+
+fn windows_segments(value: &str) -> Vec<Segment<'_>> {
+    let mut result = Vec::new();
+    let mut rest = value;
+
+    loop {
+        let Some(start) = rest.find('%') else {
+            if !rest.is_empty() {
+                result.push(Segment::Text(rest));
+            }
+            break;
+        };
+
+        let after = &rest[start + 1..];
+        match after.find('%') {
+            Some(end) if is_windows_ref_name(&after[..end]) => {
+                if start > 0 {
+                    result.push(Segment::Text(&rest[..start]));
+                }
+                result.push(Segment::Ref {
+                    name: &after[..end],
+                    raw: &rest[start..start + end + 2],
+                });
+                rest = &after[end + 1..];
+            }
+            // 两个 % 之间不是变量名，第二个 % 可能是下一个引用的开头
+            Some(end) => {
+                result.push(Segment::Text(&rest[..start + 1 + end]));
+                rest = &after[end..];
+            }
+            None => {
+                result.push(Segment::Text(rest));
+                break;
+            }
+        }
+    }
+
+    result
+}
+
+fn posix_segments(value: &str) -> Vec<Segment<'_>> {
+    let mut result = Vec::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find('$') {
+        let after = &rest[start + 1..];
+
+        let reference = if let Some(braced) = after.strip_prefix('{') {
+            braced
+                .find('}')
+                .map(|end| (&braced[..end], end + 3))
+                .filter(|(name, _)| is_posix_name(name))
+        } else {
+            let len = after
+                .find(|c: char| !(c == '_' || c.is_ascii_alphanumeric()))
+                .unwrap_or(after.len());
+            let name = &after[..len];
+            is_posix_name(name).then_some((name, len + 1))
+        };
+
+        match reference {
+            Some((name, raw_len)) => {
+                if start > 0 {
+                    result.push(Segment::Text(&rest[..start]));
+                }
+                result.push(Segment::Ref {
+                    name,
+                    raw: &rest[start..start + raw_len],
+                });
+                rest = &rest[start + raw_len..];
+            }
+            None => {
+                result.push(Segment::Text(&rest[..start + 1]));
+                rest = after;
+            }
+        }
+    }
+
+    if !rest.is_empty() {
+        result.push(Segment::Text(rest));
+    }
+    result
+}
+
+fn is_posix_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// 单个值的展开结果
+#[derive(Debug, Clone, Serialize)]
+pub struct Expansion {
+    pub value: String,
+    /// 直接引用的变量
+    pub references: Vec<String>,
+    /// 展开过程中（包括间接引用）无法解析的变量
+    pub undefined: Vec<String>,
+    /// 展开过程中遇到的循环引用链
+    pub cycle: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Resolved {
+    value: String,
+    undefined: Vec<String>,
+    cycle: Option<Vec<String>>,
+}
+
+type NodeKey = (EnvScope, String);
+
+// 系统在登录时动态设置、不保存在注册表中的变量，引用它们时从当前进程环境获取
+const WINDOWS_DYNAMIC_VARS: &[&str] = &[
+    "SystemRoot",
+    "SystemDrive",
+    "windir",
+    "USERPROFILE",
+    "USERNAME",
+    "USERDOMAIN",
+    "COMPUTERNAME",
+    "HOMEDRIVE",
+    "HOMEPATH",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "ProgramData",
+    "ALLUSERSPROFILE",
+    "PUBLIC",
+    "ProgramFiles",
+    "ProgramFiles(x86)",
+    "ProgramW6432",
+    "CommonProgramFiles",
+    "CommonProgramFiles(x86)",
+    "CommonProgramW6432",
+    "PROCESSOR_ARCHITECTURE",
+    "NUMBER_OF_PROCESSORS",
+];
+
+// POSIX 下由登录程序设置的变量
+const POSIX_DYNAMIC_VARS: &[&str] = &["HOME"];
+
+/// 基于快照的变量展开器
+///
+/// 引用按 用户 > 系统 的顺序解析，两者都没有时只有系统动态变量（如 SystemRoot、HOME）
+/// 从当前进程环境获取；用户变量引用同名变量时（如用户 Path 中的 `%Path%`）解析为系统变量。
+/// 结果会被缓存，可重复调用。
+pub struct Expander<'a> {
+    snapshot: &'a EnvSnapshot,
+    memo: HashMap<NodeKey, Resolved>,
+    stack: Vec<(NodeKey, String)>,
+    cycles: Vec<Vec<String>>,
+    process_env: bool,
+}
+
+impl<'a> Expander<'a> {
+    pub fn new(snapshot: &'a EnvSnapshot) -> Self {
+        Expander {
+            snapshot,
+            memo: HashMap::new(),
+            stack: Vec::new(),
+            cycles: Vec::new(),
+            process_env: true,
+        }
+    }
+
+    /// 不从当前进程环境获取动态变量，只按快照中的变量展开
+    pub fn without_process_env(mut self) -> Self {
+        self.process_env = false;
+        self
+    }
+
+    pub fn flavor(&self) -> EnvFlavor {
+        self.snapshot.flavor
+    }
+
+    /// 展开某个作用域中的变量，变量不存在时返回 None
+    pub fn expand_var(&mut self, scope: EnvScope, name: &str) -> Option<Expansion> {
+        let var = self.snapshot.get(scope, name)?;
+        let resolved = self.resolve_var(scope, var);
+        Some(Expansion {
+            references: self.direct_references(var),
+            value: resolved.value,
+            undefined: resolved.undefined,
+            cycle: resolved.cycle,
+        })
+    }
+
+    /// 在指定作用域的上下文中展开任意字符串（如单个 PATH 条目）
+    pub fn expand_str(&mut self, value: &str, scope: EnvScope) -> Expansion {
+        let resolved = self.expand_value(value, scope, None);
+        Expansion {
+            references: references(value, self.flavor())
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            value: resolved.value,
+            undefined: resolved.undefined,
+            cycle: resolved.cycle,
+        }
+    }
+
+    /// 进程实际看到的值：Windows 的 Path 为 系统Path;用户Path，其余变量用户优先
+    pub fn effective_value(&mut self, name: &str) -> Option<String> {
+        let flavor = self.flavor();
+        let system = self
+            .expand_var(EnvScope::System, name)
+            .map(|expansion| expansion.value);
+        let user = self
+            .expand_var(EnvScope::User, name)
+            .map(|expansion| expansion.value);
+
+        if flavor == EnvFlavor::Windows && name.eq_ignore_ascii_case("Path") {
+            let joined: Vec<String> = [system, user]
+                .into_iter()
+                .flatten()
+                .map(|value| value.trim_matches(';').to_string())
+                .filter(|value| !value.is_empty())
+                .collect();
+            return (!joined.is_empty()).then(|| joined.join(";"));
+        }
+
+        user.or(system)
+    }
+
+    /// 目前为止发现的所有循环引用
+    pub fn cycles(&self) -> &[Vec<String>] {
+        &self.cycles
+    }
+
+    fn direct_references(&self, var: &EnvVar) -> Vec<String> {
+        if var.kind != EnvValueKind::ExpandString {
+            return Vec::new();
+        }
+        references(&var.value, self.flavor())
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn key(&self, scope: EnvScope, name: &str) -> NodeKey {
+        match self.flavor() {
+            EnvFlavor::Windows => (scope, name.to_ascii_lowercase()),
+            EnvFlavor::Posix => (scope, name.to_string()),
+        }
+    }
+
+    fn resolve_var(&mut self, scope: EnvScope, var: &EnvVar) -> Resolved {
+        // 只有可展开字符串会被系统展开
+        if var.kind != EnvValueKind::ExpandString {
+            return Resolved {
+                value: var.value.clone(),
+                ..Resolved::default()
+            };
+        }
+
+        let key = self.key(scope, &var.name);
+        if let Some(resolved) = self.memo.get(&key) {
+            return resolved.clone();
+        }
+
+        self.stack.push((key.clone(), var.name.clone()));
+        let resolved = self.expand_value(&var.value, scope, Some(&var.name));
+        self.stack.pop();
+
+        self.memo.insert(key, resolved.clone());
+        resolved
+    }
+
+    fn expand_value(&mut self, value: &str, scope: EnvScope, self_name: Option<&str>) -> Resolved {
+        let flavor = self.flavor();
+        let mut resolved = Resolved::default();
+
+        for segment in segments(value, flavor) {
+            let (name, raw) = match segment {
+                Segment::Text(text) => {
+                    resolved.value.push_str(text);
+                    continue;
+                }
+                Segment::Ref { name, raw } => (name, raw),
+            };
+
+            let is_self = self_name.is_some_and(|self_name| flavor.names_equal(self_name, name));
+            match resolve_reference(self.snapshot, scope, self_name, name) {
+                Some((target_scope, var)) => {
+                    let key = self.key(target_scope, &var.name);
+                    if let Some(pos) = self.stack.iter().position(|(k, _)| *k == key) {
+                        let mut cycle: Vec<String> =
+                            self.stack[pos..].iter().map(|(_, n)| n.clone()).collect();
+                        cycle.push(var.name.clone());
+                        self.record_cycle(&cycle);
+                        resolved.cycle.get_or_insert(cycle);
+                        resolved.value.push_str(raw);
+                        continue;
+                    }
+
+                    let inner = self.resolve_var(target_scope, var);
+                    resolved.value.push_str(&inner.value);
+                    for name in inner.undefined {
+                        push_unique(&mut resolved.undefined, name);
+                    }
+                    if resolved.cycle.is_none() {
+                        resolved.cycle = inner.cycle;
+                    }
+                }
+                None => match self.dynamic_value(name) {
+                    Some(process_value) if !is_self => resolved.value.push_str(&process_value),
+                    _ => {
+                        push_unique(&mut resolved.undefined, name.to_string());
+                        resolved.value.push_str(raw);
+                    }
+                },
+            }
+        }
+
+        resolved
+    }
+
+    // 系统动态变量在当前进程环境中的值
+    fn dynamic_value(&self, name: &str) -> Option<String> {
+        if !self.process_env {
+            return None;
+        }
+        let flavor = self.flavor();
+        let dynamic = match flavor {
+            EnvFlavor::Windows => WINDOWS_DYNAMIC_VARS,
+            EnvFlavor::Posix => POSIX_DYNAMIC_VARS,
+        };
+        if !dynamic
+            .iter()
+            .any(|dynamic| flavor.names_equal(dynamic, name))
+        {
+            return None;
+        }
+        std::env::var(name).ok()
+    }
+
+    fn record_cycle(&mut self, cycle: &[String]) {
+        let mut members: Vec<String> = cycle.iter().map(|n| n.to_ascii_lowercase()).collect();
+        members.sort();
+        members.dedup();
+
+        let exists = self.cycles.iter().any(|existing| {
+            let mut other: Vec<String> = existing.iter().map(|n| n.to_ascii_lowercase()).collect();
+            other.sort();
+            other.dedup();
+            other == members
+        });
+        if !exists {
+            self.cycles.push(cycle.to_vec());
+        }
+    }
+}
+
+/// 找到 scope 中名为 self_name 的变量里对 name 的引用实际指向的变量，找不到时返回 None
+pub fn resolve_reference<'s>(
+    snapshot: &'s EnvSnapshot,
+    scope: EnvScope,
+    self_name: Option<&str>,
+    name: &str,
+) -> Option<(EnvScope, &'s EnvVar)> {
+    let is_self = self_name.is_some_and(|self_name| snapshot.flavor.names_equal(self_name, name));
+    if is_self {
+        // 用户变量引用同名变量时取系统值；系统变量自引用没有可用的值
+        return match scope {
+            EnvScope::User => snapshot
+                .get(EnvScope::System, name)
+                .map(|var| (EnvScope::System, var)),
+            EnvScope::System => None,
+        };
+    }
+
+    snapshot
+        .get(EnvScope::User, name)
+        .map(|var| (EnvScope::User, var))
+        .or_else(|| {
+            snapshot
+                .get(EnvScope::System, name)
+                .map(|var| (EnvScope::System, var))
+        })
+}
+
+fn push_unique(list: &mut Vec<String>, name: String) {
+    if !list
+        .iter()
+        .any(|existing| existing.eq_ignore_ascii_case(&name))
+    {
+        list.push(name);
+    }
+}
+
+/// 单个变量的展开信息
+#[derive(Debug, Serialize)]
+pub struct ExpandedVar {
+    pub name: String,
+    pub scope: EnvScope,
+    pub kind: EnvValueKind,
+    pub raw: String,
+    pub expanded: String,
+    pub references: Vec<String>,
+    pub undefined: Vec<String>,
+    /// 普通字符串（REG_SZ）中出现的引用，系统不会展开它们
+    pub unexpanded_references: Vec<String>,
+    pub cycle: Option<Vec<String>>,
+    /// 系统变量被同名用户变量覆盖
+    pub shadowed: bool,
+}
+
+/// 全部变量的展开报告
+#[derive(Debug, Serialize)]
+pub struct ExpansionReport {
+    pub system_vars: Vec<ExpandedVar>,
+    pub user_vars: Vec<ExpandedVar>,
+    pub undefined: Vec<String>,
+    pub cycles: Vec<Vec<String>>,
+    /// 进程最终看到的 PATH
+    pub effective_path: Option<String>,
+}
+
+pub fn build_report(snapshot: &EnvSnapshot) -> ExpansionReport {
+    let mut expander = Expander::new(snapshot);
+    let mut undefined = Vec::new();
+
+    let mut expand_scope = |expander: &mut Expander, scope: EnvScope| -> Vec<ExpandedVar> {
+        snapshot
+            .vars(scope)
+            .iter()
+            .filter_map(|var| {
+                let expansion = expander.expand_var(scope, &var.name)?;
+                for name in &expansion.undefined {
+                    push_unique(&mut undefined, name.clone());
+                }
+
+                let unexpanded_references = if var.kind == EnvValueKind::String {
+                    references(&var.value, snapshot.flavor)
+                        .into_iter()
+                        .map(str::to_string)
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+
+                Some(ExpandedVar {
+                    name: var.name.clone(),
+                    scope,
+                    kind: var.kind,
+                    raw: var.value.clone(),
+                    expanded: expansion.value,
+                    references: expansion.references,
+                    undefined: expansion.undefined,
+                    unexpanded_references,
+                    cycle: expansion.cycle,
+                    shadowed: scope == EnvScope::System
+                        && snapshot.get(EnvScope::User, &var.name).is_some(),
+                })
+            })
+            .collect()
+    };
+
+    let system_vars = expand_scope(&mut expander, EnvScope::System);
+    let user_vars = expand_scope(&mut expander, EnvScope::User);
+
+    let effective_path = expander.effective_value(snapshot.flavor.path_var_name());
+
+    undefined.sort();
+    ExpansionReport {
+        system_vars,
+        user_vars,
+        undefined,
+        cycles: expander.cycles().to_vec(),
+        effective_path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str, value: &str) -> EnvVar {
+        EnvVar {
+            name: name.to_string(),
+            value: value.to_string(),
+            kind: EnvValueKind::ExpandString,
+        }
+    }
+
+    fn snapshot(flavor: EnvFlavor, system: &[(&str, &str)], user: &[(&str, &str)]) -> EnvSnapshot {
+        EnvSnapshot {
+            flavor,
+            system_vars: system
+                .iter()
+                .map(|(name, value)| var(name, value))
+                .collect(),
+            user_vars: user.iter().map(|(name, value)| var(name, value)).collect(),
+        }
+    }
+
+    #[test]
+    fn user_vars_take_precedence_over_system() {
+        let snapshot = snapshot(
+            EnvFlavor::Windows,
+            &[("JAVA_HOME", "C:\\jdk8"), ("JAVA_BIN", "%java_home%\\bin")],
+            &[("JAVA_HOME", "C:\\jdk17")],
+        );
+        let mut expander = Expander::new(&snapshot);
+
+        let expansion = expander.expand_var(EnvScope::System, "JAVA_BIN").unwrap();
+        assert_eq!(expansion.value, "C:\\jdk17\\bin");
+        assert_eq!(expansion.references, ["java_home"]);
+        assert_eq!(
+            expander.effective_value("JAVA_HOME").as_deref(),
+            Some("C:\\jdk17")
+        );
+    }
+
+    #[test]
+    fn reports_reference_cycles() {
+        let snapshot = snapshot(EnvFlavor::Windows, &[("A", "x%B%"), ("B", "y%A%")], &[]);
+        let mut expander = Expander::new(&snapshot);
+
+        let expansion = expander.expand_var(EnvScope::System, "A").unwrap();
+        assert_eq!(
+            expansion.cycle,
+            Some(vec!["A".into(), "B".into(), "A".into()])
+        );
+        assert_eq!(expansion.value, "xy%A%");
+        assert!(expansion.undefined.is_empty());
+
+        // 同一个循环只记录一次
+        expander.expand_var(EnvScope::System, "B");
+        assert_eq!(expander.cycles().len(), 1);
+    }
+
+    #[test]
+    fn self_reference_resolves_to_system_value() {
+        let snapshot = snapshot(
+            EnvFlavor::Windows,
+            &[("Path", "C:\\Windows;%Path%")],
+            &[("Path", "%PATH%;C:\\tools")],
+        );
+        let mut expander = Expander::new(&snapshot);
+
+        // 系统变量自引用没有可用的值
+        let system = expander.expand_var(EnvScope::System, "Path").unwrap();
+        assert_eq!(system.value, "C:\\Windows;%Path%");
+        assert_eq!(system.undefined, ["Path"]);
+        assert!(system.cycle.is_none());
+
+        let user = expander.expand_var(EnvScope::User, "Path").unwrap();
+        assert_eq!(user.value, "C:\\Windows;%Path%;C:\\tools");
+        assert!(user.cycle.is_none());
+    }
+
+    #[test]
+    fn reports_undefined_references() {
+        let snapshot = snapshot(
+            EnvFlavor::Posix,
+            &[],
+            &[
+                ("GOBIN", "${GOPATH}/bin:$MY_TOOLS_EXPAND_TEST_VAR"),
+                ("CACHE", "$HOME/.cache"),
+            ],
+        );
+        // 不在动态变量列表中的进程环境变量不参与展开
+        std::env::set_var("MY_TOOLS_EXPAND_TEST_VAR", "/opt/test");
+        let mut expander = Expander::new(&snapshot);
+
+        let expansion = expander.expand_var(EnvScope::User, "GOBIN").unwrap();
+        assert_eq!(expansion.value, "${GOPATH}/bin:$MY_TOOLS_EXPAND_TEST_VAR");
+        assert_eq!(expansion.undefined, ["GOPATH", "MY_TOOLS_EXPAND_TEST_VAR"]);
+        std::env::remove_var("MY_TOOLS_EXPAND_TEST_VAR");
+
+        // HOME 是动态变量，从进程环境获取
+        if let Ok(home) = std::env::var("HOME") {
+            let expansion = expander.expand_var(EnvScope::User, "CACHE").unwrap();
+            assert_eq!(expansion.value, format!("{}/.cache", home));
+        }
+    }
+}
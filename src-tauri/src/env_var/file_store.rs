@@ -0,0 +1,276 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::store::{EnvFlavor, EnvScope, EnvStore, EnvStoreInfo};
+use super::EnvVar;
+
+// 文件内容：与导出文件的 system_vars/user_vars 字段兼容，可直接指向备份文件
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FileStoreData {
+    #[serde(default)]
+    system_vars: Vec<EnvVar>,
+    #[serde(default)]
+    user_vars: Vec<EnvVar>,
+}
+
+impl FileStoreData {
+    fn scope_vars(&mut self, scope: EnvScope) -> &mut Vec<EnvVar> {
+        match scope {
+            EnvScope::System => &mut self.system_vars,
+            EnvScope::User => &mut self.user_vars,
+        }
+    }
+}
+
+/// 基于 JSON 文件的环境变量后端，用于非 Windows 平台和测试数据
+///
+/// 变量名按 Windows 规则不区分大小写。
+pub struct FileStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileStore {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn load(&self) -> Result<FileStoreData, String> {
+        if !self.path.exists() {
+            return Ok(FileStoreData::default());
+        }
+
+        let content =
+            fs::read_to_string(&self.path).map_err(|e| format!("读取环境变量文件失败: {}", e))?;
+        if content.trim().is_empty() {
+            return Ok(FileStoreData::default());
+        }
+
+        serde_json::from_str(&content).map_err(|e| format!("解析环境变量文件失败: {}", e))
+    }
+
+    fn save(&self, data: &FileStoreData) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+        }
+
+        let json_content =
+            serde_json::to_string_pretty(data).map_err(|e| format!("序列化数据失败: {}", e))?;
+
+        write_atomic(&self.path, &json_content)
+    }
+
+    fn modify<F>(&self, scope: EnvScope, f: F) -> Result<(), String>
+    where
+        F: FnOnce(&mut Vec<EnvVar>) -> Result<(), String>,
+    {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut data = self.load()?;
+        f(data.scope_vars(scope))?;
+        self.save(&data)
+    }
+}
+
+impl EnvStore for FileStore {
+    fn info(&self) -> EnvStoreInfo {
+        EnvStoreInfo {
+            kind: "file".to_string(),
+            location: self.path.to_string_lossy().to_string(),
+            flavor: EnvFlavor::Windows,
+        }
+    }
+
+    fn list(&self, scope: EnvScope) -> Result<Vec<EnvVar>, String> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut data = self.load()?;
+        let mut vars = std::mem::take(data.scope_vars(scope));
+
+        // 按名称排序
+        vars.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(vars)
+    }
+
+    fn get(&self, scope: EnvScope, name: &str) -> Result<Option<EnvVar>, String> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut data = self.load()?;
+        Ok(std::mem::take(data.scope_vars(scope))
+            .into_iter()
+            .find(|var| var.name.eq_ignore_ascii_case(name)))
+    }
+
+    fn set(&self, scope: EnvScope, var: &EnvVar) -> Result<(), String> {
+        self.modify(scope, |vars| {
+            match vars
+                .iter_mut()
+                .find(|existing| existing.name.eq_ignore_ascii_case(&var.name))
+            {
+                // 与注册表一致：覆盖已有变量时保留原名称的大小写
+                Some(existing) => {
+                    existing.value = var.value.clone();
+                    existing.kind = var.kind;
+                }
+                None => vars.push(var.clone()),
+            }
+            Ok(())
+        })
+    }
+
+    fn delete(&self, scope: EnvScope, name: &str) -> Result<(), String> {
+        self.modify(scope, |vars| {
+            let before = vars.len();
+            vars.retain(|var| !var.name.eq_ignore_ascii_case(name));
+            if vars.len() == before {
+                return Err(format!("{}环境变量不存在: {}", scope.label(), name));
+            }
+            Ok(())
+        })
+    }
+}
+
+// 先写临时文件再重命名，避免写到一半时文件损坏；保留原文件的权限。
+// 目标是符号链接时写入链接指向的文件，不替换链接本身（如由 stow / chezmoi 管理的 ~/.profile）
+pub(super) fn write_atomic(path: &Path, content: &str) -> Result<(), String> {
+    let resolved = fs::canonicalize(path).ok();
+    let path = resolved.as_deref().unwrap_or(path);
+
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    fs::write(&tmp_path, content).map_err(|e| format!("写入文件失败: {}", e))?;
+    if let Ok(metadata) = fs::metadata(path) {
+        let _ = fs::set_permissions(&tmp_path, metadata.permissions());
+    }
+
+    fs::rename(&tmp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp_path);
+        format!("写入文件失败: {}", e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env_var::EnvValueKind;
+
+    fn var(name: &str, value: &str, kind: EnvValueKind) -> EnvVar {
+        EnvVar {
+            name: name.to_string(),
+            value: value.to_string(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn set_get_list_delete_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::new(dir.path().join("nested").join("env.json"));
+
+        assert!(store.list(EnvScope::User).unwrap().is_empty());
+        assert_eq!(store.get(EnvScope::User, "JAVA_HOME").unwrap(), None);
+
+        store
+            .set(
+                EnvScope::User,
+                &var("JAVA_HOME", "C:\\jdk", EnvValueKind::String),
+            )
+            .unwrap();
+        store
+            .set(
+                EnvScope::System,
+                &var("ComSpec", "cmd.exe", EnvValueKind::String),
+            )
+            .unwrap();
+        store
+            .set(
+                EnvScope::User,
+                &var("ANT_HOME", "C:\\ant", EnvValueKind::String),
+            )
+            .unwrap();
+
+        let names: Vec<_> = store
+            .list(EnvScope::User)
+            .unwrap()
+            .into_iter()
+            .map(|var| var.name)
+            .collect();
+        assert_eq!(names, ["ANT_HOME", "JAVA_HOME"]);
+        assert_eq!(store.list(EnvScope::System).unwrap().len(), 1);
+
+        // 重新打开文件后内容不变
+        let reopened = FileStore::new(dir.path().join("nested").join("env.json"));
+        assert_eq!(
+            reopened.get(EnvScope::User, "java_home").unwrap(),
+            Some(var("JAVA_HOME", "C:\\jdk", EnvValueKind::String))
+        );
+
+        reopened.delete(EnvScope::User, "JAVA_HOME").unwrap();
+        assert_eq!(reopened.get(EnvScope::User, "JAVA_HOME").unwrap(), None);
+        assert!(reopened.delete(EnvScope::User, "JAVA_HOME").is_err());
+        assert!(reopened.get(EnvScope::System, "ComSpec").unwrap().is_some());
+    }
+
+    #[test]
+    fn preserves_value_kind_and_name_case() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("env.json");
+        let store = FileStore::new(&path);
+
+        store
+            .set(
+                EnvScope::User,
+                &var("Path", "%USERPROFILE%\\bin", EnvValueKind::ExpandString),
+            )
+            .unwrap();
+        assert_eq!(
+            FileStore::new(&path).get(EnvScope::User, "Path").unwrap(),
+            Some(var(
+                "Path",
+                "%USERPROFILE%\\bin",
+                EnvValueKind::ExpandString
+            ))
+        );
+
+        // 覆盖时保留原名称的大小写，类型按新值写入
+        store
+            .set(
+                EnvScope::User,
+                &var("PATH", "C:\\bin", EnvValueKind::String),
+            )
+            .unwrap();
+        assert_eq!(
+            store.list(EnvScope::User).unwrap(),
+            [var("Path", "C:\\bin", EnvValueKind::String)]
+        );
+    }
+
+    #[test]
+    fn reads_backup_files_and_empty_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("backup.json");
+
+        fs::write(&path, "").unwrap();
+        assert!(FileStore::new(&path)
+            .list(EnvScope::User)
+            .unwrap()
+            .is_empty());
+
+        // 可以直接指向导出的备份文件，没有 kind 字段时按值推断
+        fs::write(
+            &path,
+            r#"{"export_info":{"export_time":"2024-01-01 00:00:00"},"system_vars":[],
+                "user_vars":[{"name":"TOOLS","value":"%HOME%\\tools"}]}"#,
+        )
+        .unwrap();
+        let tools = FileStore::new(&path)
+            .get(EnvScope::User, "TOOLS")
+            .unwrap()
+            .unwrap();
+        assert_eq!(tools.kind, EnvValueKind::ExpandString);
+    }
+}
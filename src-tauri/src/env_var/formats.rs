@@ -0,0 +1,858 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use super::backup::imported_info;
+use super::linux_store::quote_shell_value;
+use super::store::{EnvFlavor, EnvScope, SYSTEM_ENV_KEY, USER_ENV_KEY};
+use super::{EnvValueKind, EnvVar, EnvVarExport};
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Dotenv,
+    /// 调用 `[Environment]::SetEnvironmentVariable` 的 PowerShell 脚本
+    Powershell,
+    /// 由 `export` 语句组成的 POSIX shell 脚本
+    Posix,
+    /// Windows 注册表导出文件
+    Reg,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Dotenv => "env",
+            ExportFormat::Powershell => "ps1",
+            ExportFormat::Posix => "sh",
+            ExportFormat::Reg => "reg",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// 导出范围：作用域和变量子集
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ExportSelection {
+    pub scopes: Vec<EnvScope>,
+    /// 只导出这些变量，为空时导出全部
+    pub names: Option<Vec<String>>,
+}
+
+impl Default for ExportSelection {
+    fn default() -> Self {
+        ExportSelection {
+            scopes: vec![EnvScope::System, EnvScope::User],
+            names: None,
+        }
+    }
+}
+
+impl ExportSelection {
+    pub fn select<'a>(
+        &self,
+        flavor: EnvFlavor,
+        scope: EnvScope,
+        vars: &'a [EnvVar],
+    ) -> Vec<&'a EnvVar> {
+        if !self.scopes.contains(&scope) {
+            return Vec::new();
+        }
+        vars.iter()
+            .filter(|var| match &self.names {
+                Some(names) => names.iter().any(|name| flavor.names_equal(name, &var.name)),
+                None => true,
+            })
+            .collect()
+    }
+}
+
+/// 按格式生成文件内容（.reg 为带 BOM 的 UTF-16LE，其余为 UTF-8），flavor 为变量来源的平台
+pub fn render(format: ExportFormat, flavor: EnvFlavor, vars: &[(EnvScope, &EnvVar)]) -> Vec<u8> {
+    match format {
+        ExportFormat::Dotenv => render_dotenv(vars).into_bytes(),
+        ExportFormat::Powershell => render_powershell(vars).into_bytes(),
+        ExportFormat::Posix => render_posix(flavor, vars).into_bytes(),
+        ExportFormat::Reg => encode_utf16le(&render_reg(vars)),
+        ExportFormat::Csv => render_csv(vars).into_bytes(),
+    }
+}
+
+fn scope_groups<'a>(
+    vars: &'a [(EnvScope, &'a EnvVar)],
+) -> impl Iterator<Item = (EnvScope, Vec<&'a EnvVar>)> + 'a {
+    [EnvScope::System, EnvScope::User]
+        .into_iter()
+        .map(move |scope| {
+            let group: Vec<&EnvVar> = vars
+                .iter()
+                .filter(|(var_scope, _)| *var_scope == scope)
+                .map(|(_, var)| *var)
+                .collect();
+            (scope, group)
+        })
+        .filter(|(_, group)| !group.is_empty())
+}
+
+// 变量名是否能在 shell / dotenv 中使用
+fn is_shell_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn render_dotenv(vars: &[(EnvScope, &EnvVar)]) -> String {
+    let mut out = String::new();
+    // 同名变量后出现的生效，用户变量写在系统变量之后
+    for (scope, group) in scope_groups(vars) {
+        out.push_str(&format!("# {}变量\n", scope.label()));
+        for var in group {
+            if !is_shell_name(&var.name) {
+                out.push_str(&format!("# 跳过无效的变量名: {}\n", var.name));
+                continue;
+            }
+            out.push_str(&format!("{}={}\n", var.name, quote_dotenv(&var.value)));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// 单引号内的值不做任何转义和插值；含单引号或换行时改用双引号并转义
+fn quote_dotenv(value: &str) -> String {
+    if !value.contains(['\'', '\n', '\r']) {
+        return format!("'{}'", value);
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// 用于触发环境变化通知的变量名，脚本只删除它（本来就不存在），不会留下任何修改
+const POWERSHELL_BROADCAST_VAR: &str = "MY_TOOLS_ENV_REFRESH";
+
+fn render_powershell(vars: &[(EnvScope, &EnvVar)]) -> String {
+    let mut out = String::from("# 由 my-tools 导出的环境变量\n");
+    let mut wrote_registry = false;
+    for (scope, group) in scope_groups(vars) {
+        let (target, registry_path) = match scope {
+            EnvScope::System => ("Machine", format!("HKLM:\\{}", SYSTEM_ENV_KEY)),
+            EnvScope::User => ("User", format!("HKCU:\\{}", USER_ENV_KEY)),
+        };
+
+        out.push_str(&format!("\n# {}变量\n", scope.label()));
+        for var in group {
+            match var.kind {
+                // SetEnvironmentVariable 写入空值会删除该变量
+                EnvValueKind::String if var.value.is_empty() => out.push_str(&format!(
+                    "# 跳过 {}：值为空，SetEnvironmentVariable 会删除该变量\n",
+                    var.name
+                )),
+                EnvValueKind::String => out.push_str(&format!(
+                    "[Environment]::SetEnvironmentVariable({}, {}, '{}')\n",
+                    quote_powershell(&var.name),
+                    quote_powershell(&var.value),
+                    target
+                )),
+                // SetEnvironmentVariable 总是写入 REG_SZ，可展开字符串直接写注册表以保留类型
+                EnvValueKind::ExpandString => {
+                    wrote_registry = true;
+                    out.push_str(&format!(
+                        "Set-ItemProperty -Path {} -Name {} -Value {} -Type ExpandString\n",
+                        quote_powershell(&registry_path),
+                        quote_powershell(&var.name),
+                        quote_powershell(&var.value)
+                    ))
+                }
+            }
+        }
+    }
+
+    // 直接写注册表不会广播 WM_SETTINGCHANGE，借一次 SetEnvironmentVariable 通知
+    // 资源管理器和已打开的终端重新读取环境变量
+    if wrote_registry {
+        out.push_str(&format!(
+            "\n# 通知系统环境变量已更改\n[Environment]::SetEnvironmentVariable({}, $null, 'User')\n",
+            quote_powershell(POWERSHELL_BROADCAST_VAR)
+        ));
+    }
+    out
+}
+
+// PowerShell 单引号字符串：引号（包括弯引号）写两次即可，其余字符原样保留
+fn quote_powershell(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('\'');
+    for c in value.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            quoted.push(c);
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+fn render_posix(flavor: EnvFlavor, vars: &[(EnvScope, &EnvVar)]) -> String {
+    let mut out = String::from("#!/bin/sh\n# 由 my-tools 导出的环境变量\n");
+    for (scope, group) in scope_groups(vars) {
+        out.push_str(&format!("\n# {}变量\n", scope.label()));
+        for var in group {
+            if !is_shell_name(&var.name) {
+                out.push_str(&format!("# 跳过无效的变量名: {}\n", var.name));
+                continue;
+            }
+            // POSIX 下的可展开值保留 `$NAME` 引用，其余 `$`、反引号都会转义；
+            // Windows 的值使用 %VAR% 引用，在 shell 中没有意义，按字面量导出
+            let value = match (flavor, var.kind) {
+                (EnvFlavor::Posix, EnvValueKind::ExpandString) => quote_shell_value(&var.value),
+                _ => quote_single(&var.value),
+            };
+            out.push_str(&format!("export {}={}\n", var.name, value));
+        }
+    }
+    out
+}
+
+fn quote_single(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn render_reg(vars: &[(EnvScope, &EnvVar)]) -> String {
+    let mut out = String::from("Windows Registry Editor Version 5.00\r\n");
+    for (scope, group) in scope_groups(vars) {
+        let key = match scope {
+            EnvScope::System => format!("HKEY_LOCAL_MACHINE\\{}", SYSTEM_ENV_KEY),
+            EnvScope::User => format!("HKEY_CURRENT_USER\\{}", USER_ENV_KEY),
+        };
+        out.push_str(&format!("\r\n[{}]\r\n", key));
+
+        for var in group {
+            let name = format!("\"{}\"", escape_reg_string(&var.name));
+            let line = match var.kind {
+                // 普通字符串无法表示换行，改用 hex(1)
+                EnvValueKind::String if !var.value.contains(['\r', '\n']) => {
+                    format!("{}=\"{}\"", name, escape_reg_string(&var.value))
+                }
+                EnvValueKind::String => format_reg_hex(&name, 1, &var.value),
+                EnvValueKind::ExpandString => format_reg_hex(&name, 2, &var.value),
+            };
+            out.push_str(&line);
+            out.push_str("\r\n");
+        }
+    }
+    out
+}
+
+fn escape_reg_string(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// 与 regedit 相同：UTF-16LE 加结尾的 0，每行约 80 列，以 `,\` 续行
+fn format_reg_hex(name: &str, type_id: u8, value: &str) -> String {
+    let mut bytes: Vec<u8> = value.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    bytes.extend_from_slice(&[0, 0]);
+
+    let mut out = format!("{}=hex({}):", name, type_id);
+    let mut column = out.len();
+    for (index, byte) in bytes.iter().enumerate() {
+        let last = index + 1 == bytes.len();
+        let item = if last {
+            format!("{:02x}", byte)
+        } else {
+            format!("{:02x},", byte)
+        };
+        if column + item.len() > 78 && !last {
+            out.push_str("\\\r\n  ");
+            column = 2;
+        }
+        out.push_str(&item);
+        column += item.len();
+    }
+    out
+}
+
+fn encode_utf16le(content: &str) -> Vec<u8> {
+    let mut bytes = vec![0xFF, 0xFE];
+    bytes.extend(content.encode_utf16().flat_map(u16::to_le_bytes));
+    bytes
+}
+
+fn render_csv(vars: &[(EnvScope, &EnvVar)]) -> String {
+    // 带 BOM 以便 Excel 正确识别 UTF-8
+    let mut out = String::from("\u{FEFF}scope,name,value,kind\r\n");
+    for (scope, var) in vars {
+        let scope = match scope {
+            EnvScope::System => "system",
+            EnvScope::User => "user",
+        };
+        let kind = match var.kind {
+            EnvValueKind::String => "string",
+            EnvValueKind::ExpandString => "expand_string",
+        };
+        out.push_str(&format!(
+            "{},{},{},{}\r\n",
+            scope,
+            quote_csv(&var.name),
+            quote_csv(&var.value),
+            kind
+        ));
+    }
+    out
+}
+
+fn quote_csv(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// 可导入的非 JSON 格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    Dotenv,
+    Reg,
+}
+
+/// 根据文件名和内容识别 dotenv / .reg 文件，其余按 JSON 处理
+pub fn detect_import_format(path: &Path, bytes: &[u8]) -> Option<ImportFormat> {
+    let text = decode_text(bytes);
+    let header = text.trim_start();
+    if header.starts_with("Windows Registry Editor") || header.starts_with("REGEDIT4") {
+        return Some(ImportFormat::Reg);
+    }
+
+    let file_name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    let is_dotenv = file_name.starts_with(".env")
+        || path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("env"));
+    is_dotenv.then_some(ImportFormat::Dotenv)
+}
+
+/// 将 dotenv / .reg 文件转换为导出格式，导出时间取文件的修改时间
+pub fn parse_import(
+    format: ImportFormat,
+    path: &Path,
+    bytes: &[u8],
+) -> Result<EnvVarExport, String> {
+    let text = decode_text(bytes);
+    let (system_vars, user_vars) = match format {
+        ImportFormat::Dotenv => parse_dotenv(&text)?,
+        ImportFormat::Reg => parse_reg(&text)?,
+    };
+    if system_vars.is_empty() && user_vars.is_empty() {
+        return Err("文件中没有可导入的环境变量".to_string());
+    }
+
+    let export_time = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map(|time| {
+            DateTime::<Local>::from(time)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|_| "未知".to_string());
+
+    Ok(EnvVarExport {
+        export_info: imported_info(export_time),
+        system_vars,
+        user_vars,
+    })
+}
+
+// 按 BOM 解码 UTF-16LE / UTF-16BE，否则按 UTF-8 处理
+fn decode_text(bytes: &[u8]) -> String {
+    let decode_utf16 = |data: &[u8], from: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = data.chunks_exact(2).map(|c| from([c[0], c[1]])).collect();
+        String::from_utf16_lossy(&units)
+    };
+
+    match bytes {
+        [0xFF, 0xFE, rest @ ..] => decode_utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => decode_utf16(rest, u16::from_be_bytes),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest).to_string(),
+        _ => String::from_utf8_lossy(bytes).to_string(),
+    }
+}
+
+fn new_var(name: String, value: String) -> EnvVar {
+    EnvVar {
+        kind: EnvValueKind::infer(&value),
+        name,
+        value,
+    }
+}
+
+/// 解析 dotenv：支持注释、`export` 前缀、单双引号（可跨行）和双引号内的转义。
+/// 本工具导出的 `# 系统变量` 段落会导入到系统作用域，其余导入到用户作用域
+fn parse_dotenv(text: &str) -> Result<(Vec<EnvVar>, Vec<EnvVar>), String> {
+    let mut system_vars = Vec::new();
+    let mut user_vars = Vec::new();
+    let mut scope = EnvScope::User;
+    let mut lines = text.lines().enumerate();
+
+    while let Some((line_number, line)) = lines.next() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            match comment.trim() {
+                "系统变量" => scope = EnvScope::System,
+                "用户变量" => scope = EnvScope::User,
+                _ => {}
+            }
+            continue;
+        }
+
+        let assignment = trimmed
+            .strip_prefix("export ")
+            .map(str::trim_start)
+            .unwrap_or(trimmed);
+        let (name, raw_value) = assignment
+            .split_once('=')
+            .ok_or_else(|| format!("第 {} 行格式不正确: {}", line_number + 1, trimmed))?;
+        let name = name.trim();
+        if !is_shell_name(name) {
+            return Err(format!("第 {} 行变量名无效: {}", line_number + 1, name));
+        }
+
+        let raw_value = raw_value.trim_start();
+        let value = match raw_value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                // 引号未闭合时继续读取后续行
+                let mut body = raw_value[1..].to_string();
+                let end = loop {
+                    if let Some(end) = find_closing_quote(&body, quote) {
+                        break end;
+                    }
+                    match lines.next() {
+                        Some((_, next)) => {
+                            body.push('\n');
+                            body.push_str(next);
+                        }
+                        None => {
+                            return Err(format!(
+                                "第 {} 行的引号没有闭合: {}",
+                                line_number + 1,
+                                name
+                            ))
+                        }
+                    }
+                };
+                let inner = &body[..end];
+                if quote == '"' {
+                    unescape_dotenv(inner)
+                } else {
+                    inner.to_string()
+                }
+            }
+            // 未加引号的值，` #` 之后为行内注释
+            _ => match raw_value.find(" #") {
+                Some(index) => raw_value[..index].trim_end().to_string(),
+                None => raw_value.trim_end().to_string(),
+            },
+        };
+
+        let var = new_var(name.to_string(), value);
+        match scope {
+            EnvScope::System => system_vars.push(var),
+            EnvScope::User => user_vars.push(var),
+        }
+    }
+
+    Ok((system_vars, user_vars))
+}
+
+fn find_closing_quote(body: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (index, c) in body.char_indices() {
+        match c {
+            '\\' if quote == '"' && !escaped => escaped = true,
+            c if c == quote && !escaped => return Some(index),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+fn unescape_dotenv(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some(c @ ('\\' | '"' | '$')) => result.push(c),
+            // 无法识别的转义保持原样
+            Some(c) => {
+                result.push('\\');
+                result.push(c);
+            }
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// 解析注册表导出文件，只读取系统 / 用户 Environment 键下的字符串值
+fn parse_reg(text: &str) -> Result<(Vec<EnvVar>, Vec<EnvVar>), String> {
+    // REGEDIT4 格式的 hex 数据是单字节（ANSI）字符串，5.00 格式为 UTF-16LE
+    let single_byte = text.trim_start().starts_with("REGEDIT4");
+    let system_key = format!("HKEY_LOCAL_MACHINE\\{}", SYSTEM_ENV_KEY).to_lowercase();
+    let user_key = format!("HKEY_CURRENT_USER\\{}", USER_ENV_KEY).to_lowercase();
+
+    let mut system_vars = Vec::new();
+    let mut user_vars = Vec::new();
+    let mut scope = None;
+
+    for line in join_reg_lines(text) {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(key) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let key = key
+                .trim()
+                .to_lowercase()
+                .replacen("hklm\\", "hkey_local_machine\\", 1)
+                .replacen("hkcu\\", "hkey_current_user\\", 1);
+            scope = if key == system_key {
+                Some(EnvScope::System)
+            } else if key == user_key {
+                Some(EnvScope::User)
+            } else {
+                None
+            };
+            continue;
+        }
+
+        let Some(scope) = scope else {
+            continue;
+        };
+        let Some((name, rest)) = parse_reg_string(line) else {
+            continue;
+        };
+        let Some(data) = rest.trim_start().strip_prefix('=') else {
+            continue;
+        };
+        let data = data.trim();
+
+        let var = if data.starts_with('"') {
+            match parse_reg_string(data) {
+                Some((value, _)) => EnvVar {
+                    name,
+                    value,
+                    kind: EnvValueKind::String,
+                },
+                None => continue,
+            }
+        } else if let Some(hex) = data.strip_prefix("hex(2):") {
+            EnvVar {
+                name,
+                value: decode_reg_hex(hex, single_byte)?,
+                kind: EnvValueKind::ExpandString,
+            }
+        } else if let Some(hex) = data.strip_prefix("hex(1):") {
+            EnvVar {
+                name,
+                value: decode_reg_hex(hex, single_byte)?,
+                kind: EnvValueKind::String,
+            }
+        } else {
+            // 删除标记（`=-`）以及 dword 等其他类型不属于环境变量
+            continue;
+        };
+
+        match scope {
+            EnvScope::System => system_vars.push(var),
+            EnvScope::User => user_vars.push(var),
+        }
+    }
+
+    Ok((system_vars, user_vars))
+}
+
+// 合并以 `\` 结尾的续行（只有 hex 数据会折行，字符串值总是以引号结尾）
+fn join_reg_lines(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for line in text.lines() {
+        let line = line.trim();
+        match line.strip_suffix('\\') {
+            Some(head) => current.push_str(head),
+            None => {
+                current.push_str(line);
+                lines.push(std::mem::take(&mut current));
+            }
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+// 解析开头的带引号字符串，返回内容和剩余部分
+fn parse_reg_string(text: &str) -> Option<(String, &str)> {
+    let body = text.strip_prefix('"')?;
+    let mut value = String::new();
+    let mut chars = body.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => value.push(chars.next()?.1),
+            '"' => return Some((value, &body[index + 1..])),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+// hex(1)/hex(2) 数据为 UTF-16LE（REGEDIT4 为单字节字符串），去掉结尾的 0
+fn decode_reg_hex(hex: &str, single_byte: bool) -> Result<String, String> {
+    let mut bytes = hex
+        .split(',')
+        .map(str::trim)
+        .filter(|byte| !byte.is_empty())
+        .map(|byte| u8::from_str_radix(byte, 16).map_err(|e| format!("无效的十六进制数据: {}", e)))
+        .collect::<Result<Vec<u8>, String>>()?;
+
+    if single_byte {
+        while bytes.last() == Some(&0) {
+            bytes.pop();
+        }
+        // 无法得知导出时的代码页，不是有效的 UTF-8 时按 Latin-1 解码
+        return Ok(String::from_utf8(bytes)
+            .unwrap_or_else(|e| e.into_bytes().into_iter().map(char::from).collect()));
+    }
+
+    let mut units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    while units.last() == Some(&0) {
+        units.pop();
+    }
+    String::from_utf16(&units).map_err(|e| format!("无效的 UTF-16 数据: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str, value: &str, kind: EnvValueKind) -> EnvVar {
+        EnvVar {
+            name: name.to_string(),
+            value: value.to_string(),
+            kind,
+        }
+    }
+
+    fn render_text(format: ExportFormat, flavor: EnvFlavor, vars: &[EnvVar]) -> String {
+        let vars: Vec<(EnvScope, &EnvVar)> = vars.iter().map(|var| (EnvScope::User, var)).collect();
+        String::from_utf8(render(format, flavor, &vars)).unwrap()
+    }
+
+    #[test]
+    fn dotenv_quotes_values() {
+        let out = render_text(
+            ExportFormat::Dotenv,
+            EnvFlavor::Windows,
+            &[
+                var("PLAIN", "a $HOME `x`", EnvValueKind::String),
+                var("QUOTE", "it's \"ok\"\n\\", EnvValueKind::String),
+                var("BAD-NAME", "x", EnvValueKind::String),
+            ],
+        );
+        assert!(out.contains("PLAIN='a $HOME `x`'\n"));
+        assert!(out.contains("QUOTE=\"it's \\\"ok\\\"\\n\\\\\"\n"));
+        assert!(out.contains("# 跳过无效的变量名: BAD-NAME\n"));
+    }
+
+    #[test]
+    fn powershell_quotes_values_and_skips_empty_strings() {
+        let out = render_text(
+            ExportFormat::Powershell,
+            EnvFlavor::Windows,
+            &[
+                var("NAME", "it's $(calc)", EnvValueKind::String),
+                var("EMPTY", "", EnvValueKind::String),
+                var("Path", "%JAVA_HOME%\\bin", EnvValueKind::ExpandString),
+            ],
+        );
+        assert!(out
+            .contains("[Environment]::SetEnvironmentVariable('NAME', 'it''s $(calc)', 'User')\n"));
+        assert!(!out.contains("'EMPTY', ''"));
+        assert!(out.contains("# 跳过 EMPTY：值为空"));
+        assert!(out.contains(
+            "Set-ItemProperty -Path 'HKCU:\\Environment' -Name 'Path' -Value '%JAVA_HOME%\\bin' -Type ExpandString\n"
+        ));
+        assert!(out.ends_with(
+            "[Environment]::SetEnvironmentVariable('MY_TOOLS_ENV_REFRESH', $null, 'User')\n"
+        ));
+    }
+
+    #[test]
+    fn posix_keeps_references_only_for_posix_expandable_values() {
+        let vars = [
+            var("GOBIN", "${HOME}/go/bin:$PATH", EnvValueKind::ExpandString),
+            var(
+                "CMD",
+                "$(rm -rf ~) `id` pa$$word",
+                EnvValueKind::ExpandString,
+            ),
+            var("LITERAL", "it's $HOME", EnvValueKind::String),
+        ];
+
+        let out = render_text(ExportFormat::Posix, EnvFlavor::Posix, &vars);
+        assert!(out.contains("export GOBIN=\"${HOME}/go/bin:$PATH\"\n"));
+        assert!(out.contains("export CMD=\"\\$(rm -rf ~) \\`id\\` pa\\$\\$word\"\n"));
+        assert!(out.contains("export LITERAL='it'\\''s $HOME'\n"));
+
+        // Windows 的可展开值按字面量导出
+        let out = render_text(ExportFormat::Posix, EnvFlavor::Windows, &vars);
+        assert!(out.contains("export GOBIN='${HOME}/go/bin:$PATH'\n"));
+    }
+
+    #[test]
+    fn reg_escapes_strings_and_encodes_hex() {
+        let vars = [
+            var("QUOTE", "C:\\a \"b\"", EnvValueKind::String),
+            var("Path", "%A%", EnvValueKind::ExpandString),
+            var("MULTI", "a\nb", EnvValueKind::String),
+        ];
+        let vars: Vec<(EnvScope, &EnvVar)> = vars.iter().map(|var| (EnvScope::User, var)).collect();
+        let out = render_reg(&vars);
+        assert!(out.contains("\"QUOTE\"=\"C:\\\\a \\\"b\\\"\"\r\n"));
+        assert!(out.contains("\"Path\"=hex(2):25,00,41,00,25,00,00,00\r\n"));
+        assert!(out.contains("\"MULTI\"=hex(1):61,00,0a,00,62,00,00,00\r\n"));
+
+        let bytes = render(ExportFormat::Reg, EnvFlavor::Windows, &vars);
+        assert_eq!(&bytes[..2], &[0xFF, 0xFE]);
+    }
+
+    #[test]
+    fn csv_quotes_fields() {
+        let out = render_text(
+            ExportFormat::Csv,
+            EnvFlavor::Windows,
+            &[
+                var("A", "x,y \"z\"", EnvValueKind::String),
+                var("B", "plain", EnvValueKind::ExpandString),
+            ],
+        );
+        assert!(out.starts_with("\u{FEFF}scope,name,value,kind\r\n"));
+        assert!(out.contains("user,A,\"x,y \"\"z\"\"\",string\r\n"));
+        assert!(out.contains("user,B,plain,expand_string\r\n"));
+    }
+
+    #[test]
+    fn parses_dotenv() {
+        let text = "\
+# 系统变量
+export JAVA_HOME=C:\\jdk # 行内注释
+PLAIN='no $expand \\n'
+
+# 用户变量
+ESCAPED=\"a\\tb \\\"q\\\" \\$HOME\"
+MULTI=\"line1
+line2\"
+EMPTY=
+";
+        let (system, user) = parse_dotenv(text).unwrap();
+        assert_eq!(system[0], var("JAVA_HOME", "C:\\jdk", EnvValueKind::String));
+        assert_eq!(system[1].value, "no $expand \\n");
+        assert_eq!(user[0].value, "a\tb \"q\" $HOME");
+        assert_eq!(user[1].value, "line1\nline2");
+        assert_eq!(user[2].value, "");
+
+        assert!(parse_dotenv("1BAD=x").unwrap_err().contains("变量名无效"));
+        assert!(parse_dotenv("A=\"open").unwrap_err().contains("没有闭合"));
+        assert!(parse_dotenv("no equals")
+            .unwrap_err()
+            .contains("格式不正确"));
+    }
+
+    #[test]
+    fn parses_reg_exports() {
+        let text = "\
+Windows Registry Editor Version 5.00
+
+[HKEY_LOCAL_MACHINE\\SYSTEM\\CurrentControlSet\\Control\\Session Manager\\Environment]
+\"ComSpec\"=\"C:\\\\Windows\\\\cmd.exe\"
+\"Path\"=hex(2):25,00,41,00,25,00,\\
+  3b,00,43,00,00,00
+\"OLD\"=-
+\"NUMBER\"=dword:00000001
+
+[HKEY_CURRENT_USER\\Software\\Other]
+\"IGNORED\"=\"x\"
+
+[HKEY_CURRENT_USER\\Environment]
+\"Quote\"=\"say \\\"hi\\\"\"
+";
+        let (system, user) = parse_reg(text).unwrap();
+        assert_eq!(
+            system,
+            [
+                var("ComSpec", "C:\\Windows\\cmd.exe", EnvValueKind::String),
+                var("Path", "%A%;C", EnvValueKind::ExpandString),
+            ]
+        );
+        assert_eq!(user, [var("Quote", "say \"hi\"", EnvValueKind::String)]);
+
+        // 导出文件本身是带 BOM 的 UTF-16LE
+        let vars = [var(
+            "Path",
+            "%USERPROFILE%\\bin",
+            EnvValueKind::ExpandString,
+        )];
+        let vars: Vec<(EnvScope, &EnvVar)> = vars.iter().map(|var| (EnvScope::User, var)).collect();
+        let bytes = render(ExportFormat::Reg, EnvFlavor::Windows, &vars);
+        let (_, user) = parse_reg(&decode_text(&bytes)).unwrap();
+        assert_eq!(user[0].value, "%USERPROFILE%\\bin");
+    }
+
+    #[test]
+    fn parses_regedit4_single_byte_hex() {
+        let text = "\
+REGEDIT4
+
+[HKEY_CURRENT_USER\\Environment]
+\"Path\"=hex(2):25,41,25,3b,43,3a,5c,00
+\"Latin\"=hex(2):63,61,66,e9,00
+";
+        let (_, user) = parse_reg(text).unwrap();
+        assert_eq!(user[0], var("Path", "%A%;C:\\", EnvValueKind::ExpandString));
+        assert_eq!(user[1].value, "caf\u{e9}");
+    }
+}
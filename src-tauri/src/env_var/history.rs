@@ -0,0 +1,135 @@
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::backup;
+use super::file_store::write_atomic;
+use super::EnvVarExport;
+
+/// 设置后改用该目录保存自动快照（主要用于测试和便携部署）
+pub const HISTORY_DIR_VAR: &str = "MY_TOOLS_ENV_HISTORY";
+
+const SNAPSHOT_PREFIX: &str = "环境变量快照_";
+const SNAPSHOT_TIME_FORMAT: &str = "%Y%m%d_%H%M%S_%3f";
+const POLICY_FILE_NAME: &str = "policy.json";
+
+/// 自动快照的保留策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapshotPolicy {
+    /// 修改环境变量前是否自动创建快照
+    pub enabled: bool,
+    /// 保留最近的快照数量
+    pub keep_last: usize,
+    /// 在最近的天数内，每天额外保留当天最后一个快照
+    pub keep_daily_days: u32,
+}
+
+impl Default for SnapshotPolicy {
+    fn default() -> Self {
+        SnapshotPolicy {
+            enabled: true,
+            keep_last: 20,
+            keep_daily_days: 7,
+        }
+    }
+}
+
+/// 测试中让修改日志和快照写入临时目录，避免写入用户的数据目录
+#[cfg(test)]
+pub fn use_test_history_dir() {
+    let dir = std::env::temp_dir().join(format!("my-tools-test-history-{}", std::process::id()));
+    std::env::set_var(HISTORY_DIR_VAR, dir);
+}
+
+pub fn history_dir() -> PathBuf {
+    match std::env::var(HISTORY_DIR_VAR) {
+        Ok(dir) if !dir.trim().is_empty() => PathBuf::from(dir),
+        _ => dirs::data_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("my-tools")
+            .join("env_history"),
+    }
+}
+
+pub fn load_policy() -> SnapshotPolicy {
+    fs::read_to_string(history_dir().join(POLICY_FILE_NAME))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_policy(policy: &SnapshotPolicy) -> Result<(), String> {
+    let dir = history_dir();
+    fs::create_dir_all(&dir).map_err(|e| format!("创建快照目录失败: {}", e))?;
+    let content =
+        serde_json::to_string_pretty(policy).map_err(|e| format!("序列化数据失败: {}", e))?;
+    write_atomic(&dir.join(POLICY_FILE_NAME), &content)?;
+    prune(&dir, policy, Local::now().naive_local())
+}
+
+/// 按策略写入一个快照并清理过期快照，策略关闭时返回 None
+pub fn take_snapshot(data: &EnvVarExport) -> Result<Option<PathBuf>, String> {
+    let policy = load_policy();
+    if !policy.enabled {
+        return Ok(None);
+    }
+
+    let dir = history_dir();
+    fs::create_dir_all(&dir).map_err(|e| format!("创建快照目录失败: {}", e))?;
+
+    let now = Local::now().naive_local();
+    let stem = format!("{}{}", SNAPSHOT_PREFIX, now.format(SNAPSHOT_TIME_FORMAT));
+    let mut path = dir.join(format!("{}.json", stem));
+    let mut suffix = 1;
+    while path.exists() {
+        path = dir.join(format!("{}-{}.json", stem, suffix));
+        suffix += 1;
+    }
+
+    write_atomic(&path, &backup::to_json(data)?)?;
+    prune(&dir, &policy, now)?;
+
+    Ok(Some(path))
+}
+
+// 从快照文件名中解析创建时间和同一时刻的序号，不是自动快照的文件返回 None
+fn snapshot_time(path: &Path) -> Option<(NaiveDateTime, u32)> {
+    let stem = path.file_stem()?.to_str()?;
+    let rest = stem.strip_prefix(SNAPSHOT_PREFIX)?;
+    let (time, sequence) = match rest.split_once('-') {
+        Some((time, sequence)) => (time, sequence.parse().ok()?),
+        None => (rest, 0),
+    };
+    let time = NaiveDateTime::parse_from_str(time, SNAPSHOT_TIME_FORMAT).ok()?;
+    Some((time, sequence))
+}
+
+/// 删除策略之外的自动快照，目录中的其他文件不受影响
+fn prune(dir: &Path, policy: &SnapshotPolicy, now: NaiveDateTime) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("无法读取文件夹: {}", e))?;
+    let mut snapshots: Vec<((NaiveDateTime, u32), PathBuf)> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter_map(|path| snapshot_time(&path).map(|time| (time, path)))
+        .collect();
+
+    // 最新的在前
+    snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.0));
+
+    let today = now.date();
+    let oldest_daily = today - Duration::days(i64::from(policy.keep_daily_days));
+    let mut kept_days: HashSet<NaiveDate> = HashSet::new();
+
+    for (index, ((time, _), path)) in snapshots.iter().enumerate() {
+        let day = time.date();
+        let keep_daily = policy.keep_daily_days > 0 && day > oldest_daily && kept_days.insert(day);
+        if index < policy.keep_last || keep_daily {
+            continue;
+        }
+        fs::remove_file(path).map_err(|e| format!("删除过期快照失败: {}", e))?;
+    }
+
+    Ok(())
+}
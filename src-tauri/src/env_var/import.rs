@@ -0,0 +1,435 @@
+use serde::{Deserialize, Serialize};
+
+use super::journal::ChangeOrigin;
+use super::secrets;
+use super::store::{EnvScope, EnvSnapshot, EnvStore};
+use super::transaction::{apply_changes, EnvChange};
+use super::{EnvValueKind, EnvVar, EnvVarExport};
+
+/// 导入模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// 新增和更新文件中的变量，不删除任何变量
+    #[default]
+    Merge,
+    /// 使作用域与文件完全一致，删除文件中没有的变量
+    Replace,
+    /// 只新增当前不存在的变量
+    OnlyMissing,
+}
+
+/// 导入选项
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ImportOptions {
+    pub mode: ImportMode,
+    /// 要导入的作用域，默认两个都导入；其中有变量需要写入但没有写入权限时整个导入失败
+    pub scopes: Vec<EnvScope>,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions {
+            mode: ImportMode::Merge,
+            scopes: vec![EnvScope::System, EnvScope::User],
+        }
+    }
+}
+
+/// 导入后变量相对当前环境的变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    Added,
+    Changed,
+    Removed,
+    Unchanged,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VarDiff {
+    pub name: String,
+    pub scope: EnvScope,
+    pub change: DiffKind,
+    /// 当前环境中的值
+    pub old_value: Option<String>,
+    pub old_kind: Option<EnvValueKind>,
+    /// 文件中的值（only_missing 模式下可能与当前值不同但不会写入）
+    pub new_value: Option<String>,
+    pub new_kind: Option<EnvValueKind>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportPreview {
+    pub mode: ImportMode,
+    pub diff: Vec<VarDiff>,
+    pub added: usize,
+    pub changed: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+}
+
+/// 单个变量的导入结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ImportStatus {
+    /// 已写入
+    Applied,
+    /// 未写入（值未变化、导入中止等）
+    Skipped { reason: String },
+    /// 写入失败
+    Failed { error: String },
+    /// 已写入，但因其他变量失败而被撤销
+    RolledBack,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportVarResult {
+    pub name: String,
+    pub scope: EnvScope,
+    pub change: DiffKind,
+    #[serde(flatten)]
+    pub status: ImportStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReport {
+    pub mode: ImportMode,
+    pub vars: Vec<ImportVarResult>,
+    pub applied: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    /// 是否因写入失败而整体回滚
+    pub rolled_back: bool,
+    /// 回滚过程中的错误，非空时环境可能未完全恢复
+    pub rollback_errors: Vec<String>,
+    /// 导入已生效但出现的问题（如修改日志写入失败）
+    pub warning: Option<String>,
+}
+
+impl ImportReport {
+    fn new(
+        mode: ImportMode,
+        vars: Vec<ImportVarResult>,
+        rolled_back: bool,
+        rollback_errors: Vec<String>,
+    ) -> Self {
+        let count = |f: fn(&ImportStatus) -> bool| vars.iter().filter(|v| f(&v.status)).count();
+        ImportReport {
+            mode,
+            applied: count(|status| matches!(status, ImportStatus::Applied)),
+            skipped: count(|status| matches!(status, ImportStatus::Skipped { .. })),
+            failed: count(|status| matches!(status, ImportStatus::Failed { .. })),
+            vars,
+            rolled_back,
+            rollback_errors,
+            warning: None,
+        }
+    }
+
+    /// 是否写入过环境变量（回滚也算）
+    pub fn has_changes(&self) -> bool {
+        self.applied > 0 || self.rolled_back
+    }
+}
+
+/// 计算按指定模式导入后每个变量的变化
+pub fn plan_import(
+    snapshot: &EnvSnapshot,
+    data: &EnvVarExport,
+    options: &ImportOptions,
+) -> Vec<VarDiff> {
+    let flavor = snapshot.flavor;
+    let mut diff = Vec::new();
+
+    for (scope, file_vars) in [
+        (EnvScope::System, &data.system_vars),
+        (EnvScope::User, &data.user_vars),
+    ] {
+        if !options.scopes.contains(&scope) {
+            continue;
+        }
+
+        // 文件中隐藏了值的敏感变量保留当前值
+        let masked = |name: &str| secrets::is_masked(flavor, data, scope, name);
+
+        for var in file_vars
+            .iter()
+            .filter(|var| !var.name.trim().is_empty() && !masked(&var.name))
+        {
+            let current = snapshot.get(scope, &var.name);
+            let change = match current {
+                None => DiffKind::Added,
+                Some(current) if current.value == var.value && current.kind == var.kind => {
+                    DiffKind::Unchanged
+                }
+                Some(_) if options.mode == ImportMode::OnlyMissing => DiffKind::Unchanged,
+                Some(_) => DiffKind::Changed,
+            };
+
+            diff.push(VarDiff {
+                name: var.name.clone(),
+                scope,
+                change,
+                old_value: current.map(|current| current.value.clone()),
+                old_kind: current.map(|current| current.kind),
+                new_value: Some(var.value.clone()),
+                new_kind: Some(var.kind),
+            });
+        }
+
+        if options.mode == ImportMode::Replace {
+            for current in snapshot.vars(scope) {
+                let in_file = masked(&current.name)
+                    || file_vars
+                        .iter()
+                        .any(|var| flavor.names_equal(&var.name, &current.name));
+                if !in_file {
+                    diff.push(VarDiff {
+                        name: current.name.clone(),
+                        scope,
+                        change: DiffKind::Removed,
+                        old_value: Some(current.value.clone()),
+                        old_kind: Some(current.kind),
+                        new_value: None,
+                        new_kind: None,
+                    });
+                }
+            }
+        }
+    }
+
+    diff
+}
+
+pub fn preview_import(
+    snapshot: &EnvSnapshot,
+    data: &EnvVarExport,
+    options: &ImportOptions,
+) -> ImportPreview {
+    let diff = plan_import(snapshot, data, options);
+    let count = |kind: DiffKind| diff.iter().filter(|entry| entry.change == kind).count();
+
+    ImportPreview {
+        mode: options.mode,
+        added: count(DiffKind::Added),
+        changed: count(DiffKind::Changed),
+        removed: count(DiffKind::Removed),
+        unchanged: count(DiffKind::Unchanged),
+        diff,
+    }
+}
+
+/// 以事务方式导入：先对当前环境做快照，全部写入成功才算完成，否则恢复到导入前的状态。
+/// 需要写入但没有写入权限的作用域（如非管理员时的系统变量）会使整个导入在写入前失败，
+/// 调用方需要提升权限或取消选择这些作用域；before_write 只在确实有变量需要写入时调用
+pub fn run_import<F>(
+    store: &dyn EnvStore,
+    data: &EnvVarExport,
+    options: &ImportOptions,
+    before_write: F,
+) -> Result<ImportReport, String>
+where
+    F: FnOnce() -> Result<(), String>,
+{
+    let snapshot = EnvSnapshot::load(store)?;
+    let plan = plan_import(&snapshot, data, options);
+
+    let denied: Vec<&str> = [EnvScope::System, EnvScope::User]
+        .into_iter()
+        .filter(|&scope| {
+            plan.iter()
+                .any(|entry| entry.scope == scope && entry.change != DiffKind::Unchanged)
+                && !store.can_write(scope)
+        })
+        .map(EnvScope::label)
+        .collect();
+    if !denied.is_empty() {
+        return Err(format!(
+            "没有写入{}环境变量的权限，请以管理员身份运行后再导入",
+            denied.join("、")
+        ));
+    }
+
+    let mut results = Vec::new();
+    let mut changes = Vec::new();
+    // changes 中每一项对应 results 中的位置
+    let mut change_results = Vec::new();
+
+    for entry in plan {
+        let status = match entry.change {
+            DiffKind::Unchanged => ImportStatus::Skipped {
+                reason: if entry.old_value == entry.new_value && entry.old_kind == entry.new_kind {
+                    "值未变化".to_string()
+                } else {
+                    "变量已存在，保留当前值".to_string()
+                },
+            },
+            DiffKind::Added | DiffKind::Changed => {
+                change_results.push(results.len());
+                changes.push(EnvChange::Set {
+                    scope: entry.scope,
+                    var: EnvVar {
+                        name: entry.name.clone(),
+                        value: entry.new_value.clone().unwrap_or_default(),
+                        kind: entry.new_kind.unwrap_or(EnvValueKind::String),
+                    },
+                });
+                ImportStatus::Applied
+            }
+            DiffKind::Removed => {
+                change_results.push(results.len());
+                changes.push(EnvChange::Delete {
+                    scope: entry.scope,
+                    name: entry.name.clone(),
+                });
+                ImportStatus::Applied
+            }
+        };
+
+        results.push(ImportVarResult {
+            name: entry.name,
+            scope: entry.scope,
+            change: entry.change,
+            status,
+        });
+    }
+
+    if changes.is_empty() {
+        return Ok(ImportReport::new(options.mode, results, false, Vec::new()));
+    }
+
+    before_write()?;
+    match apply_changes(store, &snapshot, &changes, ChangeOrigin::Import) {
+        Ok(warning) => Ok(ImportReport {
+            warning,
+            ..ImportReport::new(options.mode, results, false, Vec::new())
+        }),
+        Err(e) => {
+            for (index, &result_index) in change_results.iter().enumerate() {
+                results[result_index].status = if index < e.failed_index {
+                    ImportStatus::RolledBack
+                } else if index == e.failed_index {
+                    ImportStatus::Failed {
+                        error: e.error.clone(),
+                    }
+                } else {
+                    ImportStatus::Skipped {
+                        reason: "导入已中止".to_string(),
+                    }
+                };
+            }
+            Ok(ImportReport::new(
+                options.mode,
+                results,
+                true,
+                e.rollback_errors,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env_var::file_store::FileStore;
+    use crate::env_var::history::use_test_history_dir;
+    use crate::env_var::store::EnvStoreInfo;
+
+    // 没有写入系统变量权限的后端
+    struct UserOnlyStore(FileStore);
+
+    impl EnvStore for UserOnlyStore {
+        fn info(&self) -> EnvStoreInfo {
+            self.0.info()
+        }
+
+        fn list(&self, scope: EnvScope) -> Result<Vec<EnvVar>, String> {
+            self.0.list(scope)
+        }
+
+        fn get(&self, scope: EnvScope, name: &str) -> Result<Option<EnvVar>, String> {
+            self.0.get(scope, name)
+        }
+
+        fn set(&self, scope: EnvScope, var: &EnvVar) -> Result<(), String> {
+            self.0.set(scope, var)
+        }
+
+        fn delete(&self, scope: EnvScope, name: &str) -> Result<(), String> {
+            self.0.delete(scope, name)
+        }
+
+        fn can_write(&self, scope: EnvScope) -> bool {
+            scope == EnvScope::User
+        }
+    }
+
+    fn export(system: serde_json::Value, user: serde_json::Value) -> EnvVarExport {
+        serde_json::from_value(serde_json::json!({
+            "export_info": { "schema_version": 2, "export_time": "2024-01-01 00:00:00" },
+            "system_vars": system,
+            "user_vars": user,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn fails_before_writing_when_a_scope_is_not_writable() {
+        use_test_history_dir();
+        let dir = tempfile::tempdir().unwrap();
+        let store = UserOnlyStore(FileStore::new(dir.path().join("env.json")));
+        let data = export(
+            serde_json::json!([{ "name": "ComSpec", "value": "cmd.exe" }]),
+            serde_json::json!([{ "name": "EDITOR", "value": "code" }]),
+        );
+
+        let mut wrote = false;
+        let error = run_import(&store, &data, &ImportOptions::default(), || {
+            wrote = true;
+            Ok(())
+        })
+        .unwrap_err();
+        assert!(error.contains("没有写入系统环境变量的权限"));
+        assert!(!wrote);
+        assert!(store.list(EnvScope::User).unwrap().is_empty());
+
+        // 取消选择系统作用域后可以导入
+        let options = ImportOptions {
+            scopes: vec![EnvScope::User],
+            ..ImportOptions::default()
+        };
+        let report = run_import(&store, &data, &options, || Ok(())).unwrap();
+        assert_eq!(report.applied, 1);
+        assert_eq!(
+            store.get(EnvScope::User, "EDITOR").unwrap().unwrap().value,
+            "code"
+        );
+    }
+
+    #[test]
+    fn unwritable_scope_without_changes_does_not_block_import() {
+        use_test_history_dir();
+        let dir = tempfile::tempdir().unwrap();
+        let store = UserOnlyStore(FileStore::new(dir.path().join("env.json")));
+        store
+            .0
+            .set(
+                EnvScope::System,
+                &EnvVar {
+                    name: "ComSpec".to_string(),
+                    value: "cmd.exe".to_string(),
+                    kind: EnvValueKind::String,
+                },
+            )
+            .unwrap();
+        let data = export(
+            serde_json::json!([{ "name": "ComSpec", "value": "cmd.exe" }]),
+            serde_json::json!([{ "name": "EDITOR", "value": "code" }]),
+        );
+
+        let report = run_import(&store, &data, &ImportOptions::default(), || Ok(())).unwrap();
+        assert_eq!((report.applied, report.skipped), (1, 1));
+    }
+}
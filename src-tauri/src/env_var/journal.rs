@@ -0,0 +1,190 @@
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use super::history::history_dir;
+use super::store::{EnvScope, EnvSnapshot};
+use super::transaction::EnvChange;
+use super::{EnvValueKind, EnvVar};
+
+const JOURNAL_FILE_NAME: &str = "journal.jsonl";
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+/// 修改的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeOrigin {
+    /// 界面上的新增、编辑和删除
+    UiEdit,
+    Import,
+    PathCleanup,
+    Undo,
+    /// 激活或停用配置方案
+    Profile,
+    /// 按团队基线修复
+    Baseline,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalValue {
+    pub value: String,
+    pub kind: EnvValueKind,
+}
+
+impl From<&EnvVar> for JournalValue {
+    fn from(var: &EnvVar) -> Self {
+        JournalValue {
+            value: var.value.clone(),
+            kind: var.kind,
+        }
+    }
+}
+
+/// 修改日志中的一条记录，old/new 为 None 表示变量不存在
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: u64,
+    pub timestamp: String,
+    pub scope: EnvScope,
+    pub name: String,
+    pub old: Option<JournalValue>,
+    pub new: Option<JournalValue>,
+    pub origin: ChangeOrigin,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UndoResult {
+    /// 被撤销的记录
+    pub undone: Vec<u64>,
+    /// 实际写入的变量数量
+    pub changes: usize,
+    /// 修改已生效但出现的问题（如修改日志写入失败）
+    pub warning: Option<String>,
+}
+
+fn journal_path() -> PathBuf {
+    history_dir().join(JOURNAL_FILE_NAME)
+}
+
+/// 读取全部记录，按写入顺序排列，无法解析的行会被忽略
+pub fn read_entries() -> Result<Vec<JournalEntry>, String> {
+    let path = journal_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path).map_err(|e| format!("读取修改日志失败: {}", e))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// 将已成功写入的修改追加到日志，旧值取自修改前的快照
+pub fn record_changes(
+    snapshot: &EnvSnapshot,
+    changes: &[EnvChange],
+    origin: ChangeOrigin,
+) -> Result<(), String> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let first_id = read_entries()?.last().map_or(1, |entry| entry.id + 1);
+    let timestamp = Local::now().format(TIMESTAMP_FORMAT).to_string();
+    let mut lines = String::new();
+
+    for (id, change) in (first_id..).zip(changes) {
+        let scope = change.scope();
+        let entry = JournalEntry {
+            id,
+            timestamp: timestamp.clone(),
+            scope,
+            name: change.name().to_string(),
+            old: snapshot.get(scope, change.name()).map(JournalValue::from),
+            new: match change {
+                EnvChange::Set { var, .. } => Some(JournalValue::from(var)),
+                EnvChange::Delete { .. } => None,
+            },
+            origin,
+        };
+
+        let line = serde_json::to_string(&entry).map_err(|e| format!("序列化数据失败: {}", e))?;
+        lines.push_str(&line);
+        lines.push('\n');
+    }
+
+    let dir = history_dir();
+    fs::create_dir_all(&dir).map_err(|e| format!("创建日志目录失败: {}", e))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(journal_path())
+        .map_err(|e| format!("打开修改日志失败: {}", e))?;
+    file.write_all(lines.as_bytes())
+        .map_err(|e| format!("写入修改日志失败: {}", e))
+}
+
+pub fn parse_timestamp(value: &str) -> Result<NaiveDateTime, String> {
+    NaiveDateTime::parse_from_str(value.trim(), TIMESTAMP_FORMAT)
+        .or_else(|_| NaiveDateTime::parse_from_str(value.trim(), "%Y-%m-%d %H:%M:%S"))
+        .map_err(|e| format!("无效的时间: {}", e))
+}
+
+/// 计算撤销一组记录所需的修改：每个变量恢复到其中最早一条记录之前的值。
+/// 变量当前的值与最后一条记录不一致（之后被其他程序修改过）时，除非 force 否则报错
+pub fn plan_undo(
+    snapshot: &EnvSnapshot,
+    entries: &[&JournalEntry],
+    force: bool,
+) -> Result<Vec<EnvChange>, String> {
+    let flavor = snapshot.flavor;
+    // (作用域, 变量名, 最早的旧值, 最后的新值)
+    let mut targets: Vec<(EnvScope, &str, &Option<JournalValue>, &Option<JournalValue>)> =
+        Vec::new();
+
+    for entry in entries {
+        match targets.iter_mut().find(|(scope, name, _, _)| {
+            *scope == entry.scope && flavor.names_equal(name, &entry.name)
+        }) {
+            Some(target) => target.3 = &entry.new,
+            None => targets.push((entry.scope, &entry.name, &entry.old, &entry.new)),
+        }
+    }
+
+    let mut changes = Vec::new();
+    for (scope, name, original, latest) in targets {
+        let current = snapshot.get(scope, name).map(JournalValue::from);
+        if !force && current.as_ref() != latest.as_ref() {
+            return Err(format!(
+                "{}变量 {} 在记录之后已被修改，如需覆盖请强制撤销",
+                scope.label(),
+                name
+            ));
+        }
+        if current.as_ref() == original.as_ref() {
+            continue;
+        }
+
+        changes.push(match original {
+            Some(original) => EnvChange::Set {
+                scope,
+                var: EnvVar {
+                    name: snapshot
+                        .get(scope, name)
+                        .map_or_else(|| name.to_string(), |var| var.name.clone()),
+                    value: original.value.clone(),
+                    kind: original.kind,
+                },
+            },
+            None => EnvChange::Delete {
+                scope,
+                name: name.to_string(),
+            },
+        });
+    }
+
+    Ok(changes)
+}
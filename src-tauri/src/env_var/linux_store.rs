@@ -0,0 +1,857 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::file_store::write_atomic;
+use super::store::{EnvFlavor, EnvScope, EnvStore, EnvStoreInfo};
+use super::{EnvValueKind, EnvVar};
+
+// 本应用在 environment.d 中管理的配置文件
+const MANAGED_CONF_NAME: &str = "90-my-tools.conf";
+const PROFILE_BLOCK_BEGIN: &str = "# >>> my-tools managed environment >>>";
+const PROFILE_BLOCK_END: &str = "# <<< my-tools managed environment <<<";
+
+/// Linux 持久化环境变量所在的文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinuxEnvPaths {
+    /// 用户级 environment.d 目录（~/.config/environment.d）
+    pub environment_d: PathBuf,
+    /// 登录 shell 读取的 ~/.profile
+    pub profile: PathBuf,
+    /// 系统级 /etc/environment
+    pub etc_environment: PathBuf,
+}
+
+impl Default for LinuxEnvPaths {
+    fn default() -> Self {
+        let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/root"));
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .unwrap_or_else(|| home.join(".config"));
+
+        LinuxEnvPaths {
+            environment_d: config_dir.join("environment.d"),
+            profile: home.join(".profile"),
+            etc_environment: PathBuf::from("/etc/environment"),
+        }
+    }
+}
+
+/// Linux 环境变量后端
+///
+/// 用户变量读取 environment.d 下所有 `*.conf` 以及 ~/.profile 中的托管块，
+/// 写入时只更新本应用的 conf 文件和托管块，其他 conf 文件属于用户或其他工具，不会被修改；
+/// 系统变量读写 /etc/environment。
+pub struct LinuxStore {
+    paths: LinuxEnvPaths,
+    lock: Mutex<()>,
+}
+
+impl LinuxStore {
+    pub fn new(paths: LinuxEnvPaths) -> Self {
+        LinuxStore {
+            paths,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn managed_conf_path(&self) -> PathBuf {
+        self.paths.environment_d.join(MANAGED_CONF_NAME)
+    }
+
+    // environment.d 中的 conf 文件，按文件名排序（后读取的覆盖先读取的）
+    fn conf_files(&self) -> Result<Vec<PathBuf>, String> {
+        if !self.paths.environment_d.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut files = Vec::new();
+        let entries = fs::read_dir(&self.paths.environment_d)
+            .map_err(|e| format!("无法读取 environment.d 目录: {}", e))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_file() && path.extension().unwrap_or_default() == "conf" {
+                files.push(path);
+            }
+        }
+
+        files.sort_by_key(|path| path.file_name().map(|name| name.to_os_string()));
+        Ok(files)
+    }
+
+    fn read_user_vars(&self) -> Result<Vec<EnvVar>, String> {
+        let mut vars: Vec<EnvVar> = Vec::new();
+
+        for file in self.conf_files()? {
+            for (name, value) in parse_assignments(&read_optional(&file)?) {
+                upsert(&mut vars, name, value);
+            }
+        }
+
+        // 托管块与 conf 文件保持同步，只补充 environment.d 中没有的变量
+        let profile = read_optional(&self.paths.profile)?;
+        for (name, value) in parse_assignments(&extract_profile_block(&profile)) {
+            if !vars.iter().any(|var| var.name == name) {
+                vars.push(posix_var(name, value));
+            }
+        }
+
+        Ok(vars)
+    }
+
+    // 其他 conf 文件中对该变量的定义，返回会覆盖本应用定义的文件（文件名排在后面）
+    // 和被本应用定义覆盖的文件
+    fn other_definitions(&self, name: &str) -> Result<(Vec<PathBuf>, Vec<PathBuf>), String> {
+        let managed_path = self.managed_conf_path();
+        let mut shadowing = Vec::new();
+        let mut shadowed = Vec::new();
+        for file in self.conf_files()? {
+            if file == managed_path || !contains_assignment(&read_optional(&file)?, name) {
+                continue;
+            }
+            if file.file_name() > managed_path.file_name() {
+                shadowing.push(file);
+            } else {
+                shadowed.push(file);
+            }
+        }
+        Ok((shadowing, shadowed))
+    }
+
+    fn write_user_vars<F>(&self, f: F) -> Result<(), String>
+    where
+        F: FnOnce(&mut Vec<EnvVar>) -> Result<(), String>,
+    {
+        let managed_path = self.managed_conf_path();
+        let mut managed: Vec<EnvVar> = parse_assignments(&read_optional(&managed_path)?)
+            .into_iter()
+            .map(|(name, value)| posix_var(name, value))
+            .collect();
+
+        // 托管块应与 conf 文件一致，若 conf 文件被手动删除则以托管块为准
+        let profile = read_optional(&self.paths.profile)?;
+        for (name, value) in parse_assignments(&extract_profile_block(&profile)) {
+            if !managed.iter().any(|var| var.name == name) {
+                managed.push(posix_var(name, value));
+            }
+        }
+
+        f(&mut managed)?;
+
+        let mut conf = String::from("# 由 my-tools 环境变量管理器生成，请勿手动修改\n");
+        for var in &managed {
+            conf.push_str(&format!("{}={}\n", var.name, quote_value(&var.value)));
+        }
+        fs::create_dir_all(&self.paths.environment_d)
+            .map_err(|e| format!("创建 environment.d 目录失败: {}", e))?;
+        write_atomic(&managed_path, &conf)?;
+
+        write_atomic(
+            &self.paths.profile,
+            &replace_profile_block(&profile, &managed),
+        )
+    }
+}
+
+impl EnvStore for LinuxStore {
+    fn info(&self) -> EnvStoreInfo {
+        EnvStoreInfo {
+            kind: "linux".to_string(),
+            location: format!(
+                "{} | {} | {}",
+                self.paths.environment_d.display(),
+                self.paths.profile.display(),
+                self.paths.etc_environment.display()
+            ),
+            flavor: EnvFlavor::Posix,
+        }
+    }
+
+    fn flavor(&self) -> EnvFlavor {
+        EnvFlavor::Posix
+    }
+
+    fn list(&self, scope: EnvScope) -> Result<Vec<EnvVar>, String> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        let mut vars = match scope {
+            EnvScope::System => {
+                let mut vars = Vec::new();
+                let content = read_optional(&self.paths.etc_environment)?;
+                for (name, value) in content.lines().filter_map(split_assignment) {
+                    upsert(&mut vars, name, unquote_pam_value(value).to_string());
+                }
+                vars
+            }
+            EnvScope::User => self.read_user_vars()?,
+        };
+
+        // 按名称排序
+        vars.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(vars)
+    }
+
+    fn get(&self, scope: EnvScope, name: &str) -> Result<Option<EnvVar>, String> {
+        Ok(self.list(scope)?.into_iter().find(|var| var.name == name))
+    }
+
+    fn set(&self, scope: EnvScope, var: &EnvVar) -> Result<(), String> {
+        if !is_valid_name(&var.name) {
+            return Err(format!("无效的环境变量名: {}", var.name));
+        }
+        if var.value.contains('\n') {
+            return Err(format!("环境变量 {} 的值不能包含换行符", var.name));
+        }
+
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        match scope {
+            EnvScope::System => {
+                let content = read_optional(&self.paths.etc_environment)?;
+                let line = format!("{}={}", var.name, quote_pam_value(&var.name, &var.value)?);
+                let updated = if contains_assignment(&content, &var.name) {
+                    // 在第一次出现的位置替换，保留文件中的注释和顺序
+                    replace_assignment_line(&content, &var.name, &line)
+                } else {
+                    append_line(&content, &line)
+                };
+                write_atomic(&self.paths.etc_environment, &updated)
+                    .map_err(|e| format!("无法设置系统环境变量（可能需要 root 权限）: {}", e))
+            }
+            EnvScope::User => {
+                // 排在本应用 conf 文件之后的定义会覆盖写入的值
+                let (shadowing, _) = self.other_definitions(&var.name)?;
+                if !shadowing.is_empty() {
+                    return Err(shadowing_error(&var.name, &shadowing));
+                }
+                self.write_user_vars(|managed| {
+                    upsert(managed, var.name.clone(), var.value.clone());
+                    Ok(())
+                })
+            }
+        }
+    }
+
+    fn delete(&self, scope: EnvScope, name: &str) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        match scope {
+            EnvScope::System => {
+                let content = read_optional(&self.paths.etc_environment)?;
+                let updated = remove_assignment_lines(&content, name)
+                    .ok_or_else(|| format!("系统环境变量不存在: {}", name))?;
+                write_atomic(&self.paths.etc_environment, &updated)
+                    .map_err(|e| format!("无法删除系统环境变量（可能需要 root 权限）: {}", e))
+            }
+            EnvScope::User => {
+                // 其他 conf 文件中的定义在删除后仍然生效，需要用户自行处理
+                let (mut others, shadowed) = self.other_definitions(name)?;
+                others.extend(shadowed);
+                if !others.is_empty() {
+                    return Err(shadowing_error(name, &others));
+                }
+                self.write_user_vars(|managed| {
+                    let before = managed.len();
+                    managed.retain(|var| var.name != name);
+                    if managed.len() == before {
+                        return Err(format!("用户环境变量不存在: {}", name));
+                    }
+                    Ok(())
+                })
+            }
+        }
+    }
+
+    fn can_write(&self, scope: EnvScope) -> bool {
+        match scope {
+            EnvScope::System => is_writable(&self.paths.etc_environment),
+            EnvScope::User => true,
+        }
+    }
+}
+
+fn shadowing_error(name: &str, files: &[PathBuf]) -> String {
+    let files: Vec<String> = files
+        .iter()
+        .map(|file| file.display().to_string())
+        .collect();
+    format!(
+        "用户环境变量 {} 同时定义在 {} 中，该文件不由本应用管理，请手动修改",
+        name,
+        files.join("、")
+    )
+}
+
+fn read_optional(path: &Path) -> Result<String, String> {
+    if !path.exists() {
+        return Ok(String::new());
+    }
+    fs::read_to_string(path).map_err(|e| format!("读取文件失败 {}: {}", path.display(), e))
+}
+
+fn is_writable(path: &Path) -> bool {
+    if path.exists() {
+        fs::OpenOptions::new().append(true).open(path).is_ok()
+    } else {
+        path.parent()
+            .and_then(|parent| fs::metadata(parent).ok())
+            .map(|meta| !meta.permissions().readonly())
+            .unwrap_or(false)
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// environment.d 会展开 ${VAR} 引用，含 `$` 的值视为可展开字符串
+fn posix_var(name: String, value: String) -> EnvVar {
+    let kind = if value.contains('$') {
+        EnvValueKind::ExpandString
+    } else {
+        EnvValueKind::String
+    };
+    EnvVar { name, value, kind }
+}
+
+fn upsert(vars: &mut Vec<EnvVar>, name: String, value: String) {
+    match vars.iter_mut().find(|var| var.name == name) {
+        Some(existing) => *existing = posix_var(name, value),
+        None => vars.push(posix_var(name, value)),
+    }
+}
+
+// 拆分一行 `[export ]NAME=VALUE`，返回未去掉引号的值；忽略空行和注释
+fn split_assignment(line: &str) -> Option<(String, &str)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+    let (name, value) = line.split_once('=')?;
+    let name = name.trim();
+    if !is_valid_name(name) {
+        return None;
+    }
+
+    Some((name.to_string(), value.trim()))
+}
+
+// environment.d 和 shell 的引号规则
+fn parse_assignment(line: &str) -> Option<(String, String)> {
+    split_assignment(line).map(|(name, value)| (name, unquote_value(value)))
+}
+
+fn parse_assignments(content: &str) -> Vec<(String, String)> {
+    content.lines().filter_map(parse_assignment).collect()
+}
+
+// 支持单引号、双引号以及 `'\''` 这样拼接的写法；双引号外只处理转义的引号
+fn unquote_value(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut quote: Option<char> = None;
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(open), c) if c == open => quote = None,
+            (None, '\\')
+                if chars
+                    .clone()
+                    .next()
+                    .is_some_and(|next| matches!(next, '\'' | '"')) =>
+            {
+                result.extend(chars.next());
+            }
+            (Some('"'), '\\') => match chars.next() {
+                Some(next @ ('"' | '\\' | '`' | '$')) => result.push(next),
+                Some(next) => {
+                    result.push('\\');
+                    result.push(next);
+                }
+                None => result.push('\\'),
+            },
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+// environment.d 的值：含有空白或特殊字符时使用双引号；保留 `$` 以便 ${VAR} 引用继续生效
+fn quote_value(value: &str) -> String {
+    let is_plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./:,@%+=${}~".contains(c));
+    if is_plain {
+        return value.to_string();
+    }
+
+    format!("\"{}\"", escape_double_quoted(value))
+}
+
+fn escape_double_quoted(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// ~/.profile 中的值：没有 `$` 时用单引号原样保留；有 `$` 时用双引号，只保留
+// `$NAME` / `${NAME}` 引用（与 environment.d 的展开一致），其余 `$`（包括 `$$`、`$(`）
+// 和反引号都转义，避免登录时被 shell 执行或替换
+pub(super) fn quote_shell_value(value: &str) -> String {
+    if !value.contains('$') {
+        return format!("'{}'", value.replace('\'', "'\\''"));
+    }
+
+    let chars: Vec<char> = value.chars().collect();
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '$' && chars.get(i + 1) == Some(&'$') {
+            quoted.push_str("\\$\\$");
+            i += 2;
+            continue;
+        }
+        if !(c == '$' && is_reference(&chars[i + 1..])) && matches!(c, '$' | '"' | '\\' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+        i += 1;
+    }
+    quoted.push('"');
+    quoted
+}
+
+// `$` 之后是否为 NAME 或 {NAME}
+fn is_reference(rest: &[char]) -> bool {
+    let is_name_start = |c: &char| c.is_ascii_alphabetic() || *c == '_';
+    match rest.first() {
+        Some(c) if is_name_start(c) => true,
+        Some('{') => {
+            let name_len = rest[1..]
+                .iter()
+                .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+                .count();
+            rest.get(1).is_some_and(is_name_start) && rest.get(name_len + 1) == Some(&'}')
+        }
+        _ => false,
+    }
+}
+
+// pam_env 读取 /etc/environment 时只去掉首尾成对的引号，不处理反斜杠转义
+fn quote_pam_value(name: &str, value: &str) -> Result<String, String> {
+    let is_plain = !value.is_empty()
+        && !value
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '#'));
+    if is_plain {
+        Ok(value.to_string())
+    } else if !value.contains('"') {
+        Ok(format!("\"{}\"", value))
+    } else if !value.contains('\'') {
+        Ok(format!("'{}'", value))
+    } else {
+        Err(format!(
+            "系统环境变量 {} 的值同时包含单引号和双引号，/etc/environment 无法保存",
+            name
+        ))
+    }
+}
+
+fn unquote_pam_value(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+            return &value[1..value.len() - 1];
+        }
+    }
+    value
+}
+
+fn contains_assignment(content: &str, name: &str) -> bool {
+    content
+        .lines()
+        .any(|line| split_assignment(line).is_some_and(|(existing, _)| existing == name))
+}
+
+// 删除所有定义该变量的行，没有找到时返回 None
+fn remove_assignment_lines(content: &str, name: &str) -> Option<String> {
+    let mut found = false;
+    let mut result = String::with_capacity(content.len());
+    for line in content.lines() {
+        if split_assignment(line).is_some_and(|(existing, _)| existing == name) {
+            found = true;
+            continue;
+        }
+        result.push_str(line);
+        result.push('\n');
+    }
+
+    found.then_some(result)
+}
+
+// 替换第一处定义并移除其余重复定义
+fn replace_assignment_line(content: &str, name: &str, new_line: &str) -> String {
+    let mut replaced = false;
+    let mut result = String::with_capacity(content.len() + new_line.len());
+    for line in content.lines() {
+        if split_assignment(line).is_some_and(|(existing, _)| existing == name) {
+            if !replaced {
+                result.push_str(new_line);
+                result.push('\n');
+                replaced = true;
+            }
+            continue;
+        }
+        result.push_str(line);
+        result.push('\n');
+    }
+    result
+}
+
+fn append_line(content: &str, line: &str) -> String {
+    let mut result = content.to_string();
+    if !result.is_empty() && !result.ends_with('\n') {
+        result.push('\n');
+    }
+    result.push_str(line);
+    result.push('\n');
+    result
+}
+
+fn extract_profile_block(profile: &str) -> String {
+    let mut inside = false;
+    let mut block = String::new();
+    for line in profile.lines() {
+        match line.trim() {
+            PROFILE_BLOCK_BEGIN => inside = true,
+            PROFILE_BLOCK_END => inside = false,
+            _ if inside => {
+                block.push_str(line);
+                block.push('\n');
+            }
+            _ => {}
+        }
+    }
+    block
+}
+
+// 重写 ~/.profile 中的托管块，其余内容保持不变；没有变量时移除整个块
+fn replace_profile_block(profile: &str, vars: &[EnvVar]) -> String {
+    let mut block = String::new();
+    if !vars.is_empty() {
+        block.push_str(PROFILE_BLOCK_BEGIN);
+        block.push('\n');
+        for var in vars {
+            block.push_str(&format!(
+                "export {}={}\n",
+                var.name,
+                quote_shell_value(&var.value)
+            ));
+        }
+        block.push_str(PROFILE_BLOCK_END);
+        block.push('\n');
+    }
+
+    let mut result = String::with_capacity(profile.len() + block.len());
+    let mut inside = false;
+    let mut inserted = false;
+    for line in profile.lines() {
+        match line.trim() {
+            PROFILE_BLOCK_BEGIN => {
+                inside = true;
+                // 移除托管块时一并去掉写入时在它前面添加的空行
+                if block.is_empty() && result.ends_with("\n\n") {
+                    result.pop();
+                }
+                if !inserted {
+                    result.push_str(&block);
+                    inserted = true;
+                }
+            }
+            PROFILE_BLOCK_END => inside = false,
+            _ if inside => {}
+            _ => {
+                result.push_str(line);
+                result.push('\n');
+            }
+        }
+    }
+
+    if block.is_empty() {
+        // 移除托管块时一并去掉它前面留下的空行
+        while result.ends_with("\n\n") {
+            result.pop();
+        }
+    } else if !inserted {
+        if !result.is_empty() && !result.ends_with("\n\n") {
+            result.push('\n');
+        }
+        result.push_str(&block);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixture {
+        dir: tempfile::TempDir,
+        store: LinuxStore,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let store = LinuxStore::new(LinuxEnvPaths {
+                environment_d: dir.path().join("environment.d"),
+                profile: dir.path().join(".profile"),
+                etc_environment: dir.path().join("environment"),
+            });
+            Fixture { dir, store }
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.dir.path().join(name)
+        }
+
+        fn write(&self, name: &str, content: &str) {
+            let path = self.path(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        fn read(&self, name: &str) -> String {
+            fs::read_to_string(self.path(name)).unwrap()
+        }
+
+        fn value(&self, scope: EnvScope, name: &str) -> Option<String> {
+            self.store.get(scope, name).unwrap().map(|var| var.value)
+        }
+    }
+
+    fn var(name: &str, value: &str) -> EnvVar {
+        posix_var(name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn reads_environment_d_in_file_name_order() {
+        let fx = Fixture::new();
+        fx.write(
+            "environment.d/50-editor.conf",
+            "# comment\nEDITOR=vim\nGOPATH=\"$HOME/go\"\n\nnot an assignment\n",
+        );
+        fx.write(
+            "environment.d/10-base.conf",
+            "EDITOR=nano\nLANG='C.UTF-8'\n",
+        );
+        fx.write("environment.d/notes.txt", "IGNORED=1\n");
+        fx.write(
+            ".profile",
+            &format!("umask 022\n{PROFILE_BLOCK_BEGIN}\nexport ONLY_PROFILE='x'\nexport EDITOR='emacs'\n{PROFILE_BLOCK_END}\n"),
+        );
+
+        let vars = fx.store.list(EnvScope::User).unwrap();
+        let names: Vec<_> = vars.iter().map(|var| var.name.as_str()).collect();
+        assert_eq!(names, ["EDITOR", "GOPATH", "LANG", "ONLY_PROFILE"]);
+        assert_eq!(fx.value(EnvScope::User, "EDITOR").as_deref(), Some("vim"));
+        assert_eq!(fx.value(EnvScope::User, "LANG").as_deref(), Some("C.UTF-8"));
+        assert_eq!(fx.value(EnvScope::User, "IGNORED"), None);
+
+        let gopath = fx.store.get(EnvScope::User, "GOPATH").unwrap().unwrap();
+        assert_eq!(gopath.value, "$HOME/go");
+        assert_eq!(gopath.kind, EnvValueKind::ExpandString);
+    }
+
+    #[test]
+    fn writes_only_managed_conf_and_profile_block() {
+        let fx = Fixture::new();
+        let other = "# 用户自己的配置\nEDITOR=vim\n";
+        fx.write("environment.d/10-user.conf", other);
+        fx.write(".profile", "umask 022\n\n. \"$HOME/.cargo/env\"\n");
+
+        fx.store
+            .set(EnvScope::User, &var("JAVA_HOME", "/opt/jdk 21"))
+            .unwrap();
+        fx.store
+            .set(EnvScope::User, &var("GOPATH", "${HOME}/go"))
+            .unwrap();
+        // 本应用的定义排在后面，会覆盖 10-user.conf 中的值
+        fx.store
+            .set(EnvScope::User, &var("EDITOR", "nano"))
+            .unwrap();
+
+        assert_eq!(fx.read("environment.d/10-user.conf"), other);
+        assert_eq!(
+            fx.read("environment.d/90-my-tools.conf"),
+            "# 由 my-tools 环境变量管理器生成，请勿手动修改\nJAVA_HOME=\"/opt/jdk 21\"\nGOPATH=${HOME}/go\nEDITOR=nano\n"
+        );
+        assert_eq!(
+            fx.read(".profile"),
+            format!(
+                "umask 022\n\n. \"$HOME/.cargo/env\"\n\n{PROFILE_BLOCK_BEGIN}\nexport JAVA_HOME='/opt/jdk 21'\nexport GOPATH=\"${{HOME}}/go\"\nexport EDITOR='nano'\n{PROFILE_BLOCK_END}\n"
+            )
+        );
+        assert_eq!(fx.value(EnvScope::User, "EDITOR").as_deref(), Some("nano"));
+
+        // 在原位置替换托管块，块外的内容保持不变
+        fx.write(
+            ".profile",
+            &format!("{}export PS1='$ '\n", fx.read(".profile")),
+        );
+        fx.store.delete(EnvScope::User, "JAVA_HOME").unwrap();
+        fx.store.delete(EnvScope::User, "GOPATH").unwrap();
+        let profile = fx.read(".profile");
+        assert!(profile.contains(&format!(
+            "{PROFILE_BLOCK_BEGIN}\nexport EDITOR='nano'\n{PROFILE_BLOCK_END}\nexport PS1='$ '\n"
+        )));
+        assert!(!profile.contains("JAVA_HOME"));
+
+        // 删除最后一个变量时移除整个托管块
+        fs::remove_file(fx.path("environment.d/10-user.conf")).unwrap();
+        fx.store.delete(EnvScope::User, "EDITOR").unwrap();
+        assert_eq!(
+            fx.read(".profile"),
+            "umask 022\n\n. \"$HOME/.cargo/env\"\nexport PS1='$ '\n"
+        );
+        assert!(fx.store.delete(EnvScope::User, "EDITOR").is_err());
+    }
+
+    #[test]
+    fn reports_definitions_in_other_conf_files() {
+        let fx = Fixture::new();
+        fx.write("environment.d/10-user.conf", "EDITOR=vim\n");
+        fx.write("environment.d/99-late.conf", "PAGER=less\n");
+
+        // 排在后面的文件会覆盖写入的值
+        let error = fx
+            .store
+            .set(EnvScope::User, &var("PAGER", "most"))
+            .unwrap_err();
+        assert!(error.contains("99-late.conf"), "{}", error);
+
+        // 删除后其他文件中的定义仍然生效
+        fx.store
+            .set(EnvScope::User, &var("EDITOR", "nano"))
+            .unwrap();
+        let error = fx.store.delete(EnvScope::User, "EDITOR").unwrap_err();
+        assert!(error.contains("10-user.conf"), "{}", error);
+
+        assert_eq!(fx.read("environment.d/10-user.conf"), "EDITOR=vim\n");
+        assert_eq!(fx.read("environment.d/99-late.conf"), "PAGER=less\n");
+        assert_eq!(fx.value(EnvScope::User, "PAGER").as_deref(), Some("less"));
+    }
+
+    #[test]
+    fn profile_values_cannot_run_commands() {
+        let fx = Fixture::new();
+        let values = [
+            "$(touch /tmp/pwned)",
+            "`id`",
+            "pa$$word",
+            "it's \"quoted\" \\ here",
+            "${HOME}/bin:$PATH",
+            "${HOME:-/root} $",
+        ];
+        for (index, value) in values.iter().enumerate() {
+            fx.store
+                .set(EnvScope::User, &var(&format!("V{}", index), value))
+                .unwrap();
+        }
+
+        let profile = fx.read(".profile");
+        assert!(profile.contains("export V0=\"\\$(touch /tmp/pwned)\"\n"));
+        assert!(profile.contains("export V1='`id`'\n"));
+        assert!(profile.contains("export V2=\"pa\\$\\$word\"\n"));
+        assert!(profile.contains("export V3='it'\\''s \"quoted\" \\ here'\n"));
+        assert!(profile.contains("export V4=\"${HOME}/bin:$PATH\"\n"));
+        assert!(profile.contains("export V5=\"\\${HOME:-/root} \\$\"\n"));
+
+        // 托管块读回的值与写入时一致
+        fs::remove_file(fx.path("environment.d/90-my-tools.conf")).unwrap();
+        for (index, value) in values.iter().enumerate() {
+            assert_eq!(
+                fx.value(EnvScope::User, &format!("V{}", index)).as_deref(),
+                Some(*value)
+            );
+        }
+    }
+
+    #[test]
+    fn edits_etc_environment_in_place() {
+        let fx = Fixture::new();
+        fx.write(
+            "environment",
+            "# system wide\nPATH=\"/usr/local/sbin:/usr/bin\"\nLANG=C\nLANG=en_US\n",
+        );
+
+        assert_eq!(
+            fx.value(EnvScope::System, "PATH").as_deref(),
+            Some("/usr/local/sbin:/usr/bin")
+        );
+        assert_eq!(fx.value(EnvScope::System, "LANG").as_deref(), Some("en_US"));
+
+        fx.store
+            .set(EnvScope::System, &var("LANG", "zh_CN.UTF-8"))
+            .unwrap();
+        fx.store
+            .set(EnvScope::System, &var("GREETING", "say \"hi\" \\o/"))
+            .unwrap();
+        fx.store
+            .set(EnvScope::System, &var("NOTE", "a b\\c"))
+            .unwrap();
+        assert_eq!(
+            fx.read("environment"),
+            "# system wide\nPATH=\"/usr/local/sbin:/usr/bin\"\nLANG=zh_CN.UTF-8\nGREETING='say \"hi\" \\o/'\nNOTE=\"a b\\c\"\n"
+        );
+        assert_eq!(
+            fx.value(EnvScope::System, "GREETING").as_deref(),
+            Some("say \"hi\" \\o/")
+        );
+        assert_eq!(
+            fx.value(EnvScope::System, "NOTE").as_deref(),
+            Some("a b\\c")
+        );
+
+        // pam_env 无法表示同时包含两种引号的值
+        assert!(fx
+            .store
+            .set(EnvScope::System, &var("BAD", "it's \"both\""))
+            .is_err());
+
+        fx.store.delete(EnvScope::System, "PATH").unwrap();
+        assert!(fx.store.delete(EnvScope::System, "PATH").is_err());
+        assert!(!fx.read("environment").contains("PATH"));
+        assert!(fx.read("environment").starts_with("# system wide\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_symlinked_profile() {
+        let fx = Fixture::new();
+        fx.write("dotfiles/profile", "umask 022\n");
+        std::os::unix::fs::symlink(fx.path("dotfiles/profile"), fx.path(".profile")).unwrap();
+
+        fx.store.set(EnvScope::User, &var("EDITOR", "vim")).unwrap();
+
+        assert!(fs::symlink_metadata(fx.path(".profile"))
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert!(fx.read("dotfiles/profile").contains("export EDITOR='vim'"));
+    }
+}
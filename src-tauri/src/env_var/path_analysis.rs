@@ -0,0 +1,409 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::expand::Expander;
+use super::store::{EnvFlavor, EnvScope, EnvSnapshot};
+use super::EnvValueKind;
+
+/// 按平台分隔符拆分 PATH，Windows 下引号内的 `;` 不作为分隔符
+pub fn split_path_list(value: &str, flavor: EnvFlavor) -> Vec<String> {
+    let separator = flavor.path_separator();
+    let mut entries = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in value.chars() {
+        match c {
+            '"' if flavor == EnvFlavor::Windows => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            c if c == separator && !in_quotes => entries.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    entries.push(current);
+
+    // 末尾的分隔符不算一个空条目
+    if entries.len() > 1 && entries.last().is_some_and(|entry| entry.is_empty()) {
+        entries.pop();
+    }
+    if entries.len() == 1 && entries[0].is_empty() {
+        entries.clear();
+    }
+    entries
+}
+
+pub fn join_path_list(entries: &[String], flavor: EnvFlavor) -> String {
+    entries.join(&flavor.path_separator().to_string())
+}
+
+/// 规范化路径用于比较：去引号、统一分隔符、处理 `.`/`..`、去掉结尾分隔符，Windows 下转小写
+pub fn normalize_path(path: &str, flavor: EnvFlavor) -> String {
+    let trimmed = path.trim().trim_matches('"').trim();
+    if trimmed.is_empty() {
+        return String::new();
+    }
+
+    let (separator, unified) = match flavor {
+        EnvFlavor::Windows => ('\\', trimmed.replace('/', "\\")),
+        EnvFlavor::Posix => ('/', trimmed.to_string()),
+    };
+
+    // 拆出前缀（盘符、UNC 或根目录），剩余部分按组件处理
+    let (prefix, rest) = if flavor == EnvFlavor::Windows && unified.starts_with("\\\\") {
+        ("\\\\".to_string(), &unified[2..])
+    } else if flavor == EnvFlavor::Windows
+        && unified.len() >= 2
+        && unified.as_bytes()[1] == b':'
+        && unified.as_bytes()[0].is_ascii_alphabetic()
+    {
+        let rooted = unified[2..].starts_with('\\');
+        let prefix = if rooted {
+            format!("{}\\", &unified[..2])
+        } else {
+            unified[..2].to_string()
+        };
+        let rest_start = if rooted { 3 } else { 2 };
+        (prefix, &unified[rest_start..])
+    } else if unified.starts_with(separator) {
+        (separator.to_string(), &unified[1..])
+    } else {
+        (String::new(), unified.as_str())
+    };
+
+    let is_absolute = !prefix.is_empty();
+    let mut components: Vec<&str> = Vec::new();
+    for component in rest.split(separator) {
+        match component {
+            "" | "." => {}
+            ".." => {
+                if components.last().is_some_and(|last| *last != "..") {
+                    components.pop();
+                } else if !is_absolute {
+                    components.push("..");
+                }
+            }
+            component => components.push(component),
+        }
+    }
+
+    let mut normalized = prefix;
+    normalized.push_str(&components.join(&separator.to_string()));
+    if normalized.is_empty() {
+        normalized.push('.');
+    }
+
+    match flavor {
+        EnvFlavor::Windows => normalized.to_lowercase(),
+        EnvFlavor::Posix => normalized,
+    }
+}
+
+/// PATH 条目存在的问题
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PathEntryIssue {
+    /// 空条目（连续的分隔符）
+    Empty,
+    /// 与前面的条目完全相同
+    ExactDuplicate { first_index: usize },
+    /// 写法不同但指向同一目录（大小写、结尾分隔符、变量展开后相同等）
+    SemanticDuplicate { first_index: usize },
+    /// 路径不存在
+    Missing,
+    /// 路径是文件而不是目录
+    NotDirectory,
+    /// 用户 PATH 中的条目与系统 PATH 重复
+    DuplicatesSystem { system_index: usize },
+    /// 引用了未定义的变量
+    UndefinedReference { names: Vec<String> },
+    /// 展开后是一个路径列表（如 `%Path%`），不单独检查
+    ListReference,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PathEntryAnalysis {
+    pub index: usize,
+    pub raw: String,
+    pub expanded: String,
+    pub normalized: String,
+    pub exists: bool,
+    pub is_dir: bool,
+    pub issues: Vec<PathEntryIssue>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScopePathAnalysis {
+    pub scope: EnvScope,
+    /// 实际的变量名（Windows 下可能是 Path 或 PATH），不存在时为 None
+    pub variable: Option<String>,
+    pub raw_length: usize,
+    pub expanded_length: usize,
+    pub entries: Vec<PathEntryAnalysis>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct PathAnalysisSummary {
+    pub total_entries: usize,
+    pub empty: usize,
+    pub exact_duplicates: usize,
+    pub semantic_duplicates: usize,
+    pub missing: usize,
+    pub not_directories: usize,
+    pub cross_scope_duplicates: usize,
+    pub undefined_references: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PathAnalysisReport {
+    pub flavor: EnvFlavor,
+    pub separator: String,
+    pub system: ScopePathAnalysis,
+    pub user: ScopePathAnalysis,
+    pub summary: PathAnalysisSummary,
+}
+
+pub fn analyze_path(snapshot: &EnvSnapshot) -> PathAnalysisReport {
+    let mut expander = Expander::new(snapshot);
+
+    let system = analyze_scope(snapshot, &mut expander, EnvScope::System);
+    let mut user = analyze_scope(snapshot, &mut expander, EnvScope::User);
+
+    // 用户 PATH 追加在系统 PATH 之后，与系统重复的条目不会生效
+    let mut system_index: HashMap<&str, usize> = HashMap::new();
+    for entry in &system.entries {
+        if !entry.normalized.is_empty() && !entry.issues.contains(&PathEntryIssue::ListReference) {
+            system_index.entry(&entry.normalized).or_insert(entry.index);
+        }
+    }
+    for entry in &mut user.entries {
+        if entry.issues.contains(&PathEntryIssue::ListReference) {
+            continue;
+        }
+        if let Some(&index) = system_index.get(entry.normalized.as_str()) {
+            entry.issues.push(PathEntryIssue::DuplicatesSystem {
+                system_index: index,
+            });
+        }
+    }
+
+    let mut summary = PathAnalysisSummary::default();
+    for entry in system.entries.iter().chain(user.entries.iter()) {
+        summary.total_entries += 1;
+        for issue in &entry.issues {
+            match issue {
+                PathEntryIssue::Empty => summary.empty += 1,
+                PathEntryIssue::ExactDuplicate { .. } => summary.exact_duplicates += 1,
+                PathEntryIssue::SemanticDuplicate { .. } => summary.semantic_duplicates += 1,
+                PathEntryIssue::Missing => summary.missing += 1,
+                PathEntryIssue::NotDirectory => summary.not_directories += 1,
+                PathEntryIssue::DuplicatesSystem { .. } => summary.cross_scope_duplicates += 1,
+                PathEntryIssue::UndefinedReference { .. } => summary.undefined_references += 1,
+                PathEntryIssue::ListReference => {}
+            }
+        }
+    }
+
+    PathAnalysisReport {
+        flavor: snapshot.flavor,
+        separator: snapshot.flavor.path_separator().to_string(),
+        system,
+        user,
+        summary,
+    }
+}
+
+fn analyze_scope(
+    snapshot: &EnvSnapshot,
+    expander: &mut Expander,
+    scope: EnvScope,
+) -> ScopePathAnalysis {
+    let flavor = snapshot.flavor;
+    let Some(var) = snapshot.get(scope, flavor.path_var_name()) else {
+        return ScopePathAnalysis {
+            scope,
+            variable: None,
+            raw_length: 0,
+            expanded_length: 0,
+            entries: Vec::new(),
+        };
+    };
+
+    let expanded_length = expander
+        .expand_var(scope, &var.name)
+        .map(|expansion| expansion.value.chars().count())
+        .unwrap_or(0);
+
+    let mut entries = Vec::new();
+    let mut seen_raw: HashMap<String, usize> = HashMap::new();
+    let mut seen_normalized: HashMap<String, usize> = HashMap::new();
+
+    for (index, raw) in split_path_list(&var.value, flavor).into_iter().enumerate() {
+        let mut issues = Vec::new();
+
+        if raw.trim().is_empty() {
+            issues.push(PathEntryIssue::Empty);
+            entries.push(PathEntryAnalysis {
+                index,
+                raw,
+                expanded: String::new(),
+                normalized: String::new(),
+                exists: false,
+                is_dir: false,
+                issues,
+            });
+            continue;
+        }
+
+        // 可展开字符串才会展开其中的引用
+        let expansion = expander.expand_str(&raw, scope);
+        let expanded = if var.kind == EnvValueKind::ExpandString {
+            expansion.value
+        } else {
+            raw.clone()
+        };
+
+        if split_path_list(&expanded, flavor).len() > 1 {
+            issues.push(PathEntryIssue::ListReference);
+            entries.push(PathEntryAnalysis {
+                index,
+                raw,
+                expanded,
+                normalized: String::new(),
+                exists: true,
+                is_dir: true,
+                issues,
+            });
+            continue;
+        }
+
+        let normalized = normalize_path(&expanded, flavor);
+
+        let raw_key = raw.trim().to_string();
+        if let Some(&first_index) = seen_raw.get(&raw_key) {
+            issues.push(PathEntryIssue::ExactDuplicate { first_index });
+        } else if let Some(&first_index) = seen_normalized.get(&normalized) {
+            issues.push(PathEntryIssue::SemanticDuplicate { first_index });
+        }
+        seen_raw.entry(raw_key).or_insert(index);
+        seen_normalized.entry(normalized.clone()).or_insert(index);
+
+        if !expansion.undefined.is_empty() && var.kind == EnvValueKind::ExpandString {
+            issues.push(PathEntryIssue::UndefinedReference {
+                names: expansion.undefined,
+            });
+        }
+
+        let target = expanded.trim().trim_matches('"');
+        let metadata = std::fs::metadata(Path::new(target)).ok();
+        let exists = metadata.is_some();
+        let is_dir = metadata.as_ref().is_some_and(|meta| meta.is_dir());
+        if !exists {
+            issues.push(PathEntryIssue::Missing);
+        } else if !is_dir {
+            issues.push(PathEntryIssue::NotDirectory);
+        }
+
+        entries.push(PathEntryAnalysis {
+            index,
+            raw,
+            expanded,
+            normalized,
+            exists,
+            is_dir,
+            issues,
+        });
+    }
+
+    ScopePathAnalysis {
+        scope,
+        variable: Some(var.name.clone()),
+        raw_length: var.value.chars().count(),
+        expanded_length,
+        entries,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env_var::EnvVar;
+
+    fn path_var(value: &str) -> EnvVar {
+        EnvVar {
+            name: "Path".to_string(),
+            value: value.to_string(),
+            kind: EnvValueKind::ExpandString,
+        }
+    }
+
+    #[test]
+    fn splits_respecting_quotes_and_trailing_separator() {
+        assert_eq!(
+            split_path_list("C:\\a;\"C:\\b;c\";;C:\\d;", EnvFlavor::Windows),
+            ["C:\\a", "\"C:\\b;c\"", "", "C:\\d"]
+        );
+        assert_eq!(
+            split_path_list("/usr/bin:\"/x:y\"", EnvFlavor::Posix),
+            ["/usr/bin", "\"/x", "y\""]
+        );
+        assert!(split_path_list("", EnvFlavor::Windows).is_empty());
+    }
+
+    #[test]
+    fn normalizes_paths_for_comparison() {
+        let windows = |path| normalize_path(path, EnvFlavor::Windows);
+        assert_eq!(windows("\"C:/Tools/./bin/\""), "c:\\tools\\bin");
+        assert_eq!(windows("C:\\a\\..\\B"), "c:\\b");
+        assert_eq!(windows("\\\\Server\\Share\\"), "\\\\server\\share");
+
+        let posix = |path| normalize_path(path, EnvFlavor::Posix);
+        assert_eq!(posix("/usr//Local/bin/"), "/usr/Local/bin");
+        assert_eq!(posix("/../a"), "/a");
+        assert_eq!(posix("../a/./b"), "../a/b");
+    }
+
+    #[test]
+    fn reports_duplicates_and_cross_scope_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let snapshot = EnvSnapshot {
+            flavor: EnvFlavor::Windows,
+            system_vars: vec![path_var(&root)],
+            user_vars: vec![
+                path_var(&format!("%TOOLS%;{0};{0};{0}\\;;%Path%", root)),
+                EnvVar {
+                    name: "ROOT_DIR".to_string(),
+                    value: root.clone(),
+                    kind: EnvValueKind::String,
+                },
+            ],
+        };
+
+        let report = analyze_path(&snapshot);
+        let issues: Vec<&Vec<PathEntryIssue>> = report
+            .user
+            .entries
+            .iter()
+            .map(|entry| &entry.issues)
+            .collect();
+        assert!(issues[0].contains(&PathEntryIssue::UndefinedReference {
+            names: vec!["TOOLS".to_string()]
+        }));
+        assert_eq!(
+            issues[1],
+            &vec![PathEntryIssue::DuplicatesSystem { system_index: 0 }]
+        );
+        assert!(issues[2].contains(&PathEntryIssue::ExactDuplicate { first_index: 1 }));
+        assert!(issues[3].contains(&PathEntryIssue::SemanticDuplicate { first_index: 1 }));
+        assert_eq!(issues[4], &vec![PathEntryIssue::Empty]);
+        assert_eq!(issues[5], &vec![PathEntryIssue::ListReference]);
+
+        assert_eq!(report.summary.total_entries, 7);
+        assert_eq!(report.summary.exact_duplicates, 1);
+        assert_eq!(report.summary.semantic_duplicates, 1);
+        assert_eq!(report.summary.cross_scope_duplicates, 3);
+    }
+}
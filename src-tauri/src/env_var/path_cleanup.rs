@@ -0,0 +1,331 @@
+use serde::{Deserialize, Serialize};
+
+use super::journal::ChangeOrigin;
+use super::path_analysis::{analyze_path, join_path_list, PathEntryIssue, ScopePathAnalysis};
+use super::store::{EnvScope, EnvSnapshot, EnvStore};
+use super::transaction::{apply_changes, EnvChange};
+use super::EnvVar;
+
+/// PATH 清理选项
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PathCleanupOptions {
+    /// 删除重复条目（保留第一次出现的位置）
+    pub remove_duplicates: bool,
+    /// 删除空条目
+    pub remove_empty: bool,
+    /// 删除不存在的路径
+    pub remove_missing: bool,
+    /// 删除用户 PATH 中与系统 PATH 重复的条目
+    pub remove_cross_scope_duplicates: bool,
+    /// 要清理的作用域，默认两个都清理
+    pub scopes: Vec<EnvScope>,
+}
+
+impl Default for PathCleanupOptions {
+    fn default() -> Self {
+        PathCleanupOptions {
+            remove_duplicates: true,
+            remove_empty: true,
+            remove_missing: false,
+            remove_cross_scope_duplicates: false,
+            scopes: vec![EnvScope::System, EnvScope::User],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemovedPathEntry {
+    pub index: usize,
+    pub raw: String,
+    /// 删除原因
+    pub reason: PathEntryIssue,
+}
+
+/// 单个作用域的清理前后对比
+#[derive(Debug, Clone, Serialize)]
+pub struct ScopePathCleanup {
+    pub scope: EnvScope,
+    pub variable: Option<String>,
+    pub before: String,
+    pub after: String,
+    pub before_length: usize,
+    pub after_length: usize,
+    pub kept: Vec<String>,
+    pub removed: Vec<RemovedPathEntry>,
+    pub changed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PathCleanupResult {
+    pub scopes: Vec<ScopePathCleanup>,
+    /// 是否已写入
+    pub applied: bool,
+    /// 修改已生效但出现的问题（如修改日志写入失败）
+    pub warning: Option<String>,
+}
+
+impl PathCleanupResult {
+    pub fn has_changes(&self) -> bool {
+        self.scopes.iter().any(|scope| scope.changed)
+    }
+}
+
+/// 根据分析结果计算清理后的 PATH，不做任何写入
+pub fn plan_cleanup(snapshot: &EnvSnapshot, options: &PathCleanupOptions) -> PathCleanupResult {
+    let report = analyze_path(snapshot);
+    let mut scopes = Vec::new();
+
+    for analysis in [&report.system, &report.user] {
+        if options.scopes.contains(&analysis.scope) {
+            scopes.push(plan_scope(snapshot, analysis, options));
+        }
+    }
+
+    PathCleanupResult {
+        scopes,
+        applied: false,
+        warning: None,
+    }
+}
+
+fn plan_scope(
+    snapshot: &EnvSnapshot,
+    analysis: &ScopePathAnalysis,
+    options: &PathCleanupOptions,
+) -> ScopePathCleanup {
+    let flavor = snapshot.flavor;
+    let before = analysis
+        .variable
+        .as_deref()
+        .and_then(|name| snapshot.get(analysis.scope, name))
+        .map(|var| var.value.clone())
+        .unwrap_or_default();
+
+    let mut kept = Vec::new();
+    let mut removed = Vec::new();
+
+    for entry in &analysis.entries {
+        let reason = entry.issues.iter().find(|issue| match issue {
+            PathEntryIssue::Empty => options.remove_empty,
+            PathEntryIssue::ExactDuplicate { .. } | PathEntryIssue::SemanticDuplicate { .. } => {
+                options.remove_duplicates
+            }
+            PathEntryIssue::Missing => options.remove_missing,
+            PathEntryIssue::DuplicatesSystem { .. } => options.remove_cross_scope_duplicates,
+            _ => false,
+        });
+
+        match reason {
+            Some(reason) => removed.push(RemovedPathEntry {
+                index: entry.index,
+                raw: entry.raw.clone(),
+                reason: reason.clone(),
+            }),
+            None => kept.push(entry.raw.clone()),
+        }
+    }
+
+    // 没有删除任何条目时保持原值，避免仅因格式差异（如结尾分隔符）而写入
+    let after = if removed.is_empty() {
+        before.clone()
+    } else {
+        join_path_list(&kept, flavor)
+    };
+
+    ScopePathCleanup {
+        scope: analysis.scope,
+        variable: analysis.variable.clone(),
+        before_length: before.chars().count(),
+        after_length: after.chars().count(),
+        changed: after != before,
+        before,
+        after,
+        kept,
+        removed,
+    }
+}
+
+/// 将清理结果写入：每个作用域只写一次，任一作用域失败时恢复已写入的作用域
+pub fn apply_cleanup(
+    store: &dyn EnvStore,
+    snapshot: &EnvSnapshot,
+    result: &mut PathCleanupResult,
+) -> Result<(), String> {
+    let mut changes = Vec::new();
+
+    for cleanup in result.scopes.iter().filter(|cleanup| cleanup.changed) {
+        let Some(original) = cleanup
+            .variable
+            .as_deref()
+            .and_then(|name| snapshot.get(cleanup.scope, name))
+        else {
+            continue;
+        };
+
+        // 保留原有的值类型（REG_EXPAND_SZ 等）
+        changes.push(EnvChange::Set {
+            scope: cleanup.scope,
+            var: EnvVar {
+                name: original.name.clone(),
+                value: cleanup.after.clone(),
+                kind: original.kind,
+            },
+        });
+    }
+
+    result.warning = apply_changes(store, snapshot, &changes, ChangeOrigin::PathCleanup)
+        .map_err(|e| e.message(&changes))?;
+
+    result.applied = true;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env_var::file_store::FileStore;
+    use crate::env_var::history::use_test_history_dir;
+    use crate::env_var::store::EnvStoreInfo;
+    use crate::env_var::EnvValueKind;
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn path_var(value: &str) -> EnvVar {
+        EnvVar {
+            name: "Path".to_string(),
+            value: value.to_string(),
+            kind: EnvValueKind::String,
+        }
+    }
+
+    fn write_paths(store: &dyn EnvStore, system: &str, user: &str) -> EnvSnapshot {
+        store.set(EnvScope::System, &path_var(system)).unwrap();
+        store.set(EnvScope::User, &path_var(user)).unwrap();
+        EnvSnapshot::load(store).unwrap()
+    }
+
+    fn only(scope: EnvScope) -> PathCleanupOptions {
+        PathCleanupOptions {
+            scopes: vec![scope],
+            ..PathCleanupOptions::default()
+        }
+    }
+
+    #[test]
+    fn removes_duplicates_keeping_first_occurrence() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::new(dir.path().join("env.json"));
+        let snapshot = write_paths(&store, "", "C:\\b;C:\\a;;c:\\B\\;C:\\c;C:\\a");
+
+        let result = plan_cleanup(&snapshot, &only(EnvScope::User));
+        let user = &result.scopes[0];
+        assert_eq!(user.after, "C:\\b;C:\\a;C:\\c");
+        assert_eq!(user.kept, ["C:\\b", "C:\\a", "C:\\c"]);
+        let removed: Vec<usize> = user.removed.iter().map(|entry| entry.index).collect();
+        assert_eq!(removed, [2, 3, 5]);
+        assert!(matches!(
+            user.removed[1].reason,
+            PathEntryIssue::SemanticDuplicate { first_index: 0 }
+        ));
+    }
+
+    #[test]
+    fn removes_missing_entries_only_when_requested() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("bin");
+        fs::create_dir(&existing).unwrap();
+        let existing = existing.to_string_lossy().to_string();
+        let missing = dir.path().join("gone").to_string_lossy().to_string();
+
+        let store = FileStore::new(dir.path().join("env.json"));
+        let snapshot = write_paths(&store, "", &format!("{};{}", missing, existing));
+
+        let result = plan_cleanup(&snapshot, &only(EnvScope::User));
+        assert!(!result.has_changes());
+
+        let options = PathCleanupOptions {
+            remove_missing: true,
+            ..only(EnvScope::User)
+        };
+        let result = plan_cleanup(&snapshot, &options);
+        assert_eq!(result.scopes[0].after, existing);
+        assert_eq!(result.scopes[0].removed[0].raw, missing);
+        assert_eq!(result.scopes[0].removed[0].reason, PathEntryIssue::Missing);
+    }
+
+    #[test]
+    fn preview_does_not_write_until_applied() {
+        use_test_history_dir();
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::new(dir.path().join("env.json"));
+        let snapshot = write_paths(&store, "C:\\sys;C:\\sys", "C:\\u;C:\\u");
+
+        let mut result = plan_cleanup(&snapshot, &PathCleanupOptions::default());
+        assert!(!result.applied);
+        assert_eq!(
+            store.get(EnvScope::User, "Path").unwrap().unwrap().value,
+            "C:\\u;C:\\u"
+        );
+
+        apply_cleanup(&store, &snapshot, &mut result).unwrap();
+        assert!(result.applied);
+        assert_eq!(
+            store.get(EnvScope::System, "Path").unwrap().unwrap().value,
+            "C:\\sys"
+        );
+        assert_eq!(
+            store.get(EnvScope::User, "Path").unwrap().unwrap().value,
+            "C:\\u"
+        );
+    }
+
+    // 第一次写入之后的写入全部失败，回滚同样失败
+    struct FailingStore {
+        inner: FileStore,
+        writes: AtomicUsize,
+    }
+
+    impl EnvStore for FailingStore {
+        fn info(&self) -> EnvStoreInfo {
+            self.inner.info()
+        }
+
+        fn list(&self, scope: EnvScope) -> Result<Vec<EnvVar>, String> {
+            self.inner.list(scope)
+        }
+
+        fn get(&self, scope: EnvScope, name: &str) -> Result<Option<EnvVar>, String> {
+            self.inner.get(scope, name)
+        }
+
+        fn set(&self, scope: EnvScope, var: &EnvVar) -> Result<(), String> {
+            if self.writes.fetch_add(1, Ordering::SeqCst) > 0 {
+                return Err("拒绝访问".to_string());
+            }
+            self.inner.set(scope, var)
+        }
+
+        fn delete(&self, _scope: EnvScope, _name: &str) -> Result<(), String> {
+            Err("拒绝访问".to_string())
+        }
+    }
+
+    #[test]
+    fn reports_failed_rollback() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = FileStore::new(dir.path().join("env.json"));
+        let snapshot = write_paths(&inner, "C:\\sys;C:\\sys", "C:\\u;C:\\u");
+        let store = FailingStore {
+            inner,
+            writes: AtomicUsize::new(0),
+        };
+
+        let mut result = plan_cleanup(&snapshot, &PathCleanupOptions::default());
+        let error = apply_cleanup(&store, &snapshot, &mut result).unwrap_err();
+        assert!(!result.applied);
+        assert!(error.starts_with("写入用户变量 Path 失败: 拒绝访问"));
+        assert!(!error.contains("已撤销"));
+        assert!(error.contains("恢复系统变量 Path 失败"));
+    }
+}
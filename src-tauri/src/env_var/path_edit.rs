@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+use super::path_analysis::{join_path_list, normalize_path, split_path_list};
+use super::store::{EnvFlavor, EnvScope, EnvSnapshot};
+use super::{EnvValueKind, EnvVar};
+
+/// 对路径列表变量的一次编辑，条目按规范化后的路径匹配
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PathEdit {
+    /// 在 index 处插入，index 为空或超出末尾时追加到末尾
+    Insert {
+        entry: String,
+        #[serde(default)]
+        index: Option<usize>,
+    },
+    /// 移动条目，offset 为负数时向前移动
+    Move {
+        entry: String,
+        offset: isize,
+    },
+    /// 移除条目（包括重复出现的）
+    Remove {
+        entry: String,
+    },
+    Replace {
+        old: String,
+        new: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PathEditResult {
+    pub name: String,
+    pub scope: EnvScope,
+    pub value: String,
+    pub kind: EnvValueKind,
+    pub entries: Vec<String>,
+    /// 是否实际写入（编辑后值未变化时不写入）
+    pub changed: bool,
+    /// 修改已生效但出现的问题（如修改日志写入失败）
+    pub warning: Option<String>,
+}
+
+/// 在快照中的变量值上依次应用编辑，返回编辑后的变量；变量不存在时视为空列表
+pub fn apply_edits(
+    snapshot: &EnvSnapshot,
+    scope: EnvScope,
+    name: &str,
+    edits: &[PathEdit],
+) -> Result<EnvVar, String> {
+    let flavor = snapshot.flavor;
+    let existing = snapshot.get(scope, name);
+    let mut entries = existing
+        .map(|var| split_path_list(&var.value, flavor))
+        .unwrap_or_default();
+
+    for edit in edits {
+        apply_edit(flavor, &mut entries, edit)?;
+    }
+
+    let value = join_path_list(&entries, flavor);
+    Ok(EnvVar {
+        name: existing.map_or_else(|| name.to_string(), |var| var.name.clone()),
+        kind: existing
+            .map(|var| var.kind)
+            .unwrap_or_else(|| EnvValueKind::infer(&value)),
+        value,
+    })
+}
+
+fn apply_edit(flavor: EnvFlavor, entries: &mut Vec<String>, edit: &PathEdit) -> Result<(), String> {
+    let position = |entries: &[String], entry: &str| {
+        let key = normalize_path(entry, flavor);
+        entries
+            .iter()
+            .position(|existing| normalize_path(existing, flavor) == key)
+    };
+    let not_found = |entry: &str| format!("列表中不存在条目: {}", entry);
+
+    match edit {
+        PathEdit::Insert { entry, index } => {
+            let entry = entry.trim();
+            if entry.is_empty() {
+                return Err("条目不能为空".to_string());
+            }
+            if position(entries, entry).is_some() {
+                return Err(format!("列表中已存在条目: {}", entry));
+            }
+            let index = index.unwrap_or(entries.len()).min(entries.len());
+            entries.insert(index, entry.to_string());
+        }
+        PathEdit::Move { entry, offset } => {
+            let from = position(entries, entry).ok_or_else(|| not_found(entry))?;
+            let to = from
+                .saturating_add_signed(*offset)
+                .min(entries.len().saturating_sub(1));
+            let moved = entries.remove(from);
+            entries.insert(to, moved);
+        }
+        PathEdit::Remove { entry } => {
+            let key = normalize_path(entry, flavor);
+            let count = entries.len();
+            entries.retain(|existing| normalize_path(existing, flavor) != key);
+            if entries.len() == count {
+                return Err(not_found(entry));
+            }
+        }
+        PathEdit::Replace { old, new } => {
+            let new = new.trim();
+            if new.is_empty() {
+                return Err("条目不能为空".to_string());
+            }
+            let index = position(entries, old).ok_or_else(|| not_found(old))?;
+            if position(entries, new).is_some_and(|existing| existing != index) {
+                return Err(format!("列表中已存在条目: {}", new));
+            }
+            entries[index] = new.to_string();
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,614 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use super::file_store::write_atomic;
+use super::journal::JournalValue;
+use super::path_analysis::{join_path_list, normalize_path, split_path_list};
+use super::store::{EnvScope, EnvSnapshot};
+use super::transaction::EnvChange;
+use super::{EnvValueKind, EnvVar};
+
+/// 设置后改用该文件保存配置方案（主要用于测试和便携部署）
+pub const PROFILES_FILE_VAR: &str = "MY_TOOLS_ENV_PROFILES";
+
+/// 配置方案中要覆盖的变量，kind 为空时沿用已有变量的类型或根据值推断
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileVar {
+    pub name: String,
+    pub value: String,
+    #[serde(default)]
+    pub kind: Option<EnvValueKind>,
+}
+
+/// 一组可以整体切换的环境变量配置，例如 "JDK 17" 或 "Node 20"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvProfile {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// 写入的作用域，默认用户变量
+    #[serde(default = "default_scope")]
+    pub scope: EnvScope,
+    #[serde(default)]
+    pub vars: Vec<ProfileVar>,
+    /// 添加到 PATH 最前面的条目
+    #[serde(default)]
+    pub path_add: Vec<String>,
+    /// 从 PATH 中移除的条目（按规范化后的路径匹配）
+    #[serde(default)]
+    pub path_remove: Vec<String>,
+}
+
+fn default_scope() -> EnvScope {
+    EnvScope::User
+}
+
+/// 方案激活前变量的值以及激活时写入的值，None 表示变量不存在
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedVar {
+    pub name: String,
+    pub previous: Option<JournalValue>,
+    pub applied: Option<JournalValue>,
+}
+
+/// 激活时从 PATH 中移除的条目，index 为它在激活前 PATH 中的位置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedPathEntry {
+    pub index: usize,
+    pub entry: String,
+}
+
+/// 方案对 PATH 做的修改，停用时只撤销这些修改，保留激活期间其他程序对 PATH 的修改
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedPathEdit {
+    pub name: String,
+    /// 添加到最前面的条目
+    pub added: Vec<String>,
+    /// 从激活前的 PATH 中移除的条目（包括因移到最前面而移除的原有条目）
+    pub removed: Vec<SavedPathEntry>,
+    /// 激活前 PATH 是否存在
+    pub existed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveProfile {
+    pub name: String,
+    pub scope: EnvScope,
+    pub activated_at: String,
+    /// 被覆盖的变量，停用时恢复为激活前的整个值
+    pub saved: Vec<SavedVar>,
+    #[serde(default)]
+    pub path: Option<SavedPathEdit>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileState {
+    pub profiles: Vec<EnvProfile>,
+    pub active: Option<ActiveProfile>,
+}
+
+/// 当前激活的方案
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveProfileStatus {
+    pub name: String,
+    pub scope: EnvScope,
+    pub activated_at: String,
+    /// 激活后又被修改过的（被覆盖的）变量，停用时这些变量同样会恢复为激活前的值；
+    /// PATH 不在其中，停用时只撤销方案自己添加和移除的条目
+    pub modified: Vec<String>,
+    /// 激活时修改已生效但出现的问题（如修改日志写入失败）
+    pub warning: Option<String>,
+}
+
+fn profiles_path() -> PathBuf {
+    match std::env::var(PROFILES_FILE_VAR) {
+        Ok(path) if !path.trim().is_empty() => PathBuf::from(path),
+        _ => dirs::data_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("my-tools")
+            .join("env_profiles.json"),
+    }
+}
+
+pub fn load_state() -> Result<ProfileState, String> {
+    let path = profiles_path();
+    if !path.exists() {
+        return Ok(ProfileState::default());
+    }
+
+    let content = fs::read_to_string(&path).map_err(|e| format!("读取配置方案失败: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("解析配置方案失败: {}", e))
+}
+
+pub fn save_state(state: &ProfileState) -> Result<(), String> {
+    let path = profiles_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建文件夹失败: {}", e))?;
+    }
+    let content =
+        serde_json::to_string_pretty(state).map_err(|e| format!("序列化数据失败: {}", e))?;
+    write_atomic(&path, &content)
+}
+
+/// 检查方案内容，名称不能为空且变量不能重复
+pub fn validate_profile(profile: &EnvProfile, snapshot: &EnvSnapshot) -> Result<(), String> {
+    if profile.name.trim().is_empty() {
+        return Err("配置方案名称不能为空".to_string());
+    }
+
+    let flavor = snapshot.flavor;
+    for (index, var) in profile.vars.iter().enumerate() {
+        if var.name.trim().is_empty() {
+            return Err("变量名不能为空".to_string());
+        }
+        if flavor.names_equal(&var.name, flavor.path_var_name())
+            && !(profile.path_add.is_empty() && profile.path_remove.is_empty())
+        {
+            return Err(format!(
+                "方案同时覆盖了 {} 并设置了要添加或移除的条目",
+                flavor.path_var_name()
+            ));
+        }
+        if profile.vars[..index]
+            .iter()
+            .any(|other| flavor.names_equal(&other.name, &var.name))
+        {
+            return Err(format!("变量 {} 重复", var.name));
+        }
+    }
+
+    Ok(())
+}
+
+/// 计算激活 profile 所需的修改以及激活后的状态。已有激活的方案时先在内存中将其恢复原状，
+/// 再在恢复后的环境上应用新方案，最终合并成一组修改以便一次性写入
+pub fn plan_activate(
+    snapshot: &EnvSnapshot,
+    active: Option<&ActiveProfile>,
+    profile: &EnvProfile,
+) -> (Vec<EnvChange>, ActiveProfile) {
+    let mut working = snapshot.clone();
+    let mut touched = match active {
+        Some(active) => restore(&mut working, active),
+        None => Vec::new(),
+    };
+
+    let scope = profile.scope;
+    let targets = var_targets(&working, profile);
+    let saved = targets
+        .iter()
+        .map(|(name, applied)| SavedVar {
+            name: name.clone(),
+            previous: working.get(scope, name).map(JournalValue::from),
+            applied: Some(applied.clone()),
+        })
+        .collect();
+
+    for (name, applied) in &targets {
+        set_value(&mut working, scope, name, Some(applied));
+        touched.push((scope, name.clone()));
+    }
+
+    let path = apply_path_edit(&mut working, profile);
+    if let Some(path) = &path {
+        touched.push((scope, path.name.clone()));
+    }
+
+    let active = ActiveProfile {
+        name: profile.name.clone(),
+        scope,
+        activated_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        saved,
+        path,
+    };
+    (changes_between(snapshot, &working, touched), active)
+}
+
+/// 计算停用方案所需的修改：把方案覆盖的变量恢复为激活前的值，并撤销它对 PATH 的修改
+pub fn plan_deactivate(snapshot: &EnvSnapshot, active: &ActiveProfile) -> Vec<EnvChange> {
+    let mut working = snapshot.clone();
+    let touched = restore(&mut working, active);
+    changes_between(snapshot, &working, touched)
+}
+
+// 在内存中撤销方案的修改，返回涉及的变量
+fn restore(working: &mut EnvSnapshot, active: &ActiveProfile) -> Vec<(EnvScope, String)> {
+    let mut touched: Vec<(EnvScope, String)> = active
+        .saved
+        .iter()
+        .map(|saved| {
+            set_value(working, active.scope, &saved.name, saved.previous.as_ref());
+            (active.scope, saved.name.clone())
+        })
+        .collect();
+
+    if let Some(path) = &active.path {
+        undo_path_edit(working, active.scope, path);
+        touched.push((active.scope, path.name.clone()));
+    }
+    touched
+}
+
+// 对比涉及的变量在两个快照中的值，生成从 current 变为 target 的修改
+fn changes_between(
+    current: &EnvSnapshot,
+    target: &EnvSnapshot,
+    touched: Vec<(EnvScope, String)>,
+) -> Vec<EnvChange> {
+    let flavor = current.flavor;
+    let mut changes: Vec<EnvChange> = Vec::new();
+
+    for (scope, name) in touched {
+        if changes
+            .iter()
+            .any(|change| change.scope() == scope && flavor.names_equal(change.name(), &name))
+        {
+            continue;
+        }
+
+        let existing = current.get(scope, &name);
+        match target.get(scope, &name) {
+            Some(var)
+                if existing.is_some_and(|existing| {
+                    existing.value == var.value && existing.kind == var.kind
+                }) => {}
+            Some(var) => changes.push(EnvChange::Set {
+                scope,
+                var: var.clone(),
+            }),
+            None if existing.is_some() => changes.push(EnvChange::Delete { scope, name }),
+            None => {}
+        }
+    }
+
+    changes
+}
+
+// 方案覆盖的变量激活后的目标值
+fn var_targets(snapshot: &EnvSnapshot, profile: &EnvProfile) -> Vec<(String, JournalValue)> {
+    let scope = profile.scope;
+    profile
+        .vars
+        .iter()
+        .map(|var| {
+            let kind = var.kind.unwrap_or_else(|| {
+                snapshot
+                    .get(scope, &var.name)
+                    .map(|existing| existing.kind)
+                    .unwrap_or_else(|| EnvValueKind::infer(&var.value))
+            });
+            (
+                var.name.clone(),
+                JournalValue {
+                    value: var.value.clone(),
+                    kind,
+                },
+            )
+        })
+        .collect()
+}
+
+// 在内存中把 path_add 的条目放到 PATH 最前面并移除 path_remove 的条目，返回所做的修改
+fn apply_path_edit(working: &mut EnvSnapshot, profile: &EnvProfile) -> Option<SavedPathEdit> {
+    if profile.path_add.is_empty() && profile.path_remove.is_empty() {
+        return None;
+    }
+
+    let flavor = working.flavor;
+    let scope = profile.scope;
+    let existing = working.get(scope, flavor.path_var_name());
+    let name = existing.map_or_else(
+        || flavor.path_var_name().to_string(),
+        |var| var.name.clone(),
+    );
+    let existing_kind = existing.map(|var| var.kind);
+    let mut entries = existing
+        .map(|var| split_path_list(&var.value, flavor))
+        .unwrap_or_default();
+
+    let dropped: Vec<String> = profile
+        .path_remove
+        .iter()
+        .chain(&profile.path_add)
+        .map(|entry| normalize_path(entry, flavor))
+        .collect();
+    let removed = entries
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| dropped.contains(&normalize_path(entry, flavor)))
+        .map(|(index, entry)| SavedPathEntry {
+            index,
+            entry: entry.clone(),
+        })
+        .collect();
+    entries.retain(|entry| !dropped.contains(&normalize_path(entry, flavor)));
+
+    let added: Vec<String> = profile
+        .path_add
+        .iter()
+        .filter(|entry| !entry.trim().is_empty())
+        .cloned()
+        .collect();
+    entries.splice(0..0, added.clone());
+
+    let value = join_path_list(&entries, flavor);
+    let kind = existing_kind.unwrap_or_else(|| EnvValueKind::infer(&value));
+    set_value(working, scope, &name, Some(&JournalValue { value, kind }));
+
+    Some(SavedPathEdit {
+        name,
+        added,
+        removed,
+        existed: existing_kind.is_some(),
+    })
+}
+
+// 在内存中撤销 apply_path_edit 的修改：移除方案添加的条目，把移除的条目放回原位置，
+// 激活期间其他程序添加的条目保持不变
+fn undo_path_edit(working: &mut EnvSnapshot, scope: EnvScope, edit: &SavedPathEdit) {
+    let flavor = working.flavor;
+    let existing = working.get(scope, &edit.name);
+    let kind = existing.map(|var| var.kind);
+    let mut entries = existing
+        .map(|var| split_path_list(&var.value, flavor))
+        .unwrap_or_default();
+
+    let added: Vec<String> = edit
+        .added
+        .iter()
+        .map(|entry| normalize_path(entry, flavor))
+        .collect();
+    entries.retain(|entry| !added.contains(&normalize_path(entry, flavor)));
+
+    for removed in &edit.removed {
+        let normalized = normalize_path(&removed.entry, flavor);
+        if !entries
+            .iter()
+            .any(|entry| normalize_path(entry, flavor) == normalized)
+        {
+            entries.insert(removed.index.min(entries.len()), removed.entry.clone());
+        }
+    }
+
+    if entries.is_empty() && !edit.existed {
+        set_value(working, scope, &edit.name, None);
+        return;
+    }
+    let value = join_path_list(&entries, flavor);
+    let kind = kind.unwrap_or_else(|| EnvValueKind::infer(&value));
+    set_value(
+        working,
+        scope,
+        &edit.name,
+        Some(&JournalValue { value, kind }),
+    );
+}
+
+// 在内存中的快照上设置或删除变量，保留已有变量名的大小写
+fn set_value(
+    snapshot: &mut EnvSnapshot,
+    scope: EnvScope,
+    name: &str,
+    value: Option<&JournalValue>,
+) {
+    let flavor = snapshot.flavor;
+    let vars = match scope {
+        EnvScope::System => &mut snapshot.system_vars,
+        EnvScope::User => &mut snapshot.user_vars,
+    };
+    let position = vars
+        .iter()
+        .position(|var| flavor.names_equal(&var.name, name));
+
+    match (value, position) {
+        (Some(value), Some(index)) => {
+            vars[index].value = value.value.clone();
+            vars[index].kind = value.kind;
+        }
+        (Some(value), None) => vars.push(EnvVar {
+            name: name.to_string(),
+            value: value.value.clone(),
+            kind: value.kind,
+        }),
+        (None, Some(index)) => {
+            vars.remove(index);
+        }
+        (None, None) => {}
+    }
+}
+
+pub fn active_status(snapshot: &EnvSnapshot, active: &ActiveProfile) -> ActiveProfileStatus {
+    ActiveProfileStatus {
+        name: active.name.clone(),
+        scope: active.scope,
+        activated_at: active.activated_at.clone(),
+        modified: active
+            .saved
+            .iter()
+            .filter(|saved| {
+                snapshot
+                    .get(active.scope, &saved.name)
+                    .map(JournalValue::from)
+                    != saved.applied
+            })
+            .map(|saved| saved.name.clone())
+            .collect(),
+        warning: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env_var::store::EnvFlavor;
+
+    fn var(name: &str, value: &str, kind: EnvValueKind) -> EnvVar {
+        EnvVar {
+            name: name.to_string(),
+            value: value.to_string(),
+            kind,
+        }
+    }
+
+    fn snapshot(user_vars: Vec<EnvVar>) -> EnvSnapshot {
+        EnvSnapshot {
+            flavor: EnvFlavor::Windows,
+            system_vars: Vec::new(),
+            user_vars,
+        }
+    }
+
+    fn profile(json: serde_json::Value) -> EnvProfile {
+        serde_json::from_value(json).unwrap()
+    }
+
+    // 把修改应用到内存中的快照上，代替实际写入
+    fn apply(snapshot: &EnvSnapshot, changes: &[EnvChange]) -> EnvSnapshot {
+        let mut result = snapshot.clone();
+        for change in changes {
+            let vars = &mut result.user_vars;
+            vars.retain(|var| !var.name.eq_ignore_ascii_case(change.name()));
+            if let EnvChange::Set { var, .. } = change {
+                vars.push(var.clone());
+            }
+        }
+        result
+    }
+
+    fn value<'a>(snapshot: &'a EnvSnapshot, name: &str) -> Option<&'a str> {
+        snapshot
+            .get(EnvScope::User, name)
+            .map(|var| var.value.as_str())
+    }
+
+    fn jdk17() -> EnvProfile {
+        profile(serde_json::json!({
+            "name": "jdk17",
+            "vars": [{ "name": "JAVA_HOME", "value": "C:\\jdk17" }],
+            "path_add": ["C:\\jdk17\\bin"],
+            "path_remove": ["c:\\jdk8\\bin\\"],
+        }))
+    }
+
+    #[test]
+    fn deactivate_restores_exact_values() {
+        let before = snapshot(vec![
+            var("JAVA_HOME", "%TOOLS%\\jdk8", EnvValueKind::ExpandString),
+            var(
+                "Path",
+                "C:\\a;C:\\jdk8\\bin;C:\\b",
+                EnvValueKind::ExpandString,
+            ),
+        ]);
+        let profile = profile(serde_json::json!({
+            "name": "jdk17",
+            "vars": [
+                { "name": "java_home", "value": "C:\\jdk17", "kind": "string" },
+                { "name": "JDK_NEW", "value": "1" },
+            ],
+            "path_add": ["C:\\jdk17\\bin"],
+            "path_remove": ["c:\\jdk8\\bin\\"],
+        }));
+
+        let (changes, active) = plan_activate(&before, None, &profile);
+        let activated = apply(&before, &changes);
+        assert_eq!(value(&activated, "JAVA_HOME"), Some("C:\\jdk17"));
+        assert_eq!(value(&activated, "JDK_NEW"), Some("1"));
+        assert_eq!(
+            value(&activated, "Path"),
+            Some("C:\\jdk17\\bin;C:\\a;C:\\b")
+        );
+
+        let restored = apply(&activated, &plan_deactivate(&activated, &active));
+        let mut expected = before.user_vars.clone();
+        let mut actual = restored.user_vars.clone();
+        expected.sort_by(|a, b| a.name.cmp(&b.name));
+        actual.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn deactivate_keeps_path_entries_added_while_active() {
+        let before = snapshot(vec![var(
+            "Path",
+            "C:\\jdk8\\bin;C:\\tools",
+            EnvValueKind::String,
+        )]);
+        let (changes, active) = plan_activate(&before, None, &jdk17());
+        let mut activated = apply(&before, &changes);
+        assert_eq!(value(&activated, "Path"), Some("C:\\jdk17\\bin;C:\\tools"));
+
+        // 激活期间安装程序修改了 PATH
+        let path = activated
+            .user_vars
+            .iter_mut()
+            .find(|var| var.name == "Path")
+            .unwrap();
+        path.value = "C:\\jdk17\\bin;C:\\tools;C:\\installer".to_string();
+
+        let restored = apply(&activated, &plan_deactivate(&activated, &active));
+        assert_eq!(
+            value(&restored, "Path"),
+            Some("C:\\jdk8\\bin;C:\\tools;C:\\installer")
+        );
+    }
+
+    #[test]
+    fn switching_restores_previous_profile_before_applying_next() {
+        let before = snapshot(vec![
+            var("JAVA_HOME", "C:\\jdk8", EnvValueKind::String),
+            var("Path", "C:\\jdk8\\bin;C:\\tools", EnvValueKind::String),
+        ]);
+        let (changes, active) = plan_activate(&before, None, &jdk17());
+        let activated = apply(&before, &changes);
+
+        let node = profile(serde_json::json!({
+            "name": "node",
+            "vars": [
+                { "name": "JAVA_HOME", "value": "C:\\jdk8" },
+                { "name": "NODE_HOME", "value": "C:\\node20" },
+            ],
+            "path_add": ["C:\\node20"],
+        }));
+        let (changes, switched) = plan_activate(&activated, Some(&active), &node);
+
+        // 每个变量只修改一次；JAVA_HOME 恢复后与 node 方案的值相同，但仍需写回
+        let names: Vec<&str> = changes.iter().map(EnvChange::name).collect();
+        assert_eq!(names, ["JAVA_HOME", "Path", "NODE_HOME"]);
+
+        let after = apply(&activated, &changes);
+        assert_eq!(value(&after, "JAVA_HOME"), Some("C:\\jdk8"));
+        assert_eq!(value(&after, "NODE_HOME"), Some("C:\\node20"));
+        assert_eq!(
+            value(&after, "Path"),
+            Some("C:\\node20;C:\\jdk8\\bin;C:\\tools")
+        );
+
+        // 新方案保存的是恢复后（即激活 jdk17 之前）的值
+        assert_eq!(
+            switched.saved[0].previous.as_ref().unwrap().value,
+            "C:\\jdk8"
+        );
+        let restored = apply(&after, &plan_deactivate(&after, &switched));
+        assert_eq!(value(&restored, "Path"), value(&before, "Path"));
+        assert_eq!(value(&restored, "NODE_HOME"), None);
+    }
+
+    #[test]
+    fn deactivate_deletes_path_created_by_profile() {
+        let before = snapshot(Vec::new());
+        let (changes, active) = plan_activate(&before, None, &jdk17());
+        let activated = apply(&before, &changes);
+        assert_eq!(value(&activated, "Path"), Some("C:\\jdk17\\bin"));
+        assert!(!active.path.as_ref().unwrap().existed);
+
+        let changes = plan_deactivate(&activated, &active);
+        assert!(changes
+            .iter()
+            .all(|change| matches!(change, EnvChange::Delete { .. })));
+        assert!(apply(&activated, &changes).user_vars.is_empty());
+    }
+}
@@ -0,0 +1,153 @@
+use winreg::enums::*;
+use winreg::types::{FromRegValue, ToRegValue};
+use winreg::RegKey;
+
+use super::store::{EnvFlavor, EnvScope, EnvStore, EnvStoreInfo, SYSTEM_ENV_KEY, USER_ENV_KEY};
+use super::{EnvValueKind, EnvVar};
+
+/// Windows 注册表后端（HKLM/HKCU 下的 Environment 键）
+pub struct RegistryStore;
+
+impl RegistryStore {
+    fn open_read(scope: EnvScope) -> Result<RegKey, String> {
+        match scope {
+            EnvScope::System => RegKey::predef(HKEY_LOCAL_MACHINE)
+                .open_subkey(SYSTEM_ENV_KEY)
+                .map_err(|e| format!("无法打开系统环境变量注册表: {}", e)),
+            EnvScope::User => RegKey::predef(HKEY_CURRENT_USER)
+                .open_subkey(USER_ENV_KEY)
+                .map_err(|e| format!("无法打开用户环境变量注册表: {}", e)),
+        }
+    }
+
+    fn open_write(scope: EnvScope) -> Result<RegKey, String> {
+        match scope {
+            // 系统环境变量需要管理员权限
+            EnvScope::System => RegKey::predef(HKEY_LOCAL_MACHINE)
+                .open_subkey_with_flags(SYSTEM_ENV_KEY, KEY_WRITE)
+                .map_err(|e| {
+                    format!(
+                        "无法打开系统环境变量注册表进行写入（可能需要管理员权限）: {}",
+                        e
+                    )
+                }),
+            EnvScope::User => RegKey::predef(HKEY_CURRENT_USER)
+                .open_subkey_with_flags(USER_ENV_KEY, KEY_WRITE)
+                .map_err(|e| format!("无法打开用户环境变量注册表进行写入: {}", e)),
+        }
+    }
+}
+
+impl EnvStore for RegistryStore {
+    fn info(&self) -> EnvStoreInfo {
+        EnvStoreInfo {
+            kind: "registry".to_string(),
+            location: format!("HKLM\\{} | HKCU\\{}", SYSTEM_ENV_KEY, USER_ENV_KEY),
+            flavor: EnvFlavor::Windows,
+        }
+    }
+
+    fn list(&self, scope: EnvScope) -> Result<Vec<EnvVar>, String> {
+        let env_key = Self::open_read(scope)?;
+
+        let mut vars = Vec::new();
+
+        for (name, value) in env_key.enum_values().filter_map(|x| x.ok()) {
+            let kind = match value.vtype {
+                REG_SZ => EnvValueKind::String,
+                REG_EXPAND_SZ => EnvValueKind::ExpandString,
+                _ => continue, // 跳过非字符串类型的值
+            };
+            let value_str = env_key.get_value::<String, _>(&name).unwrap_or_default();
+
+            vars.push(EnvVar {
+                name,
+                value: value_str,
+                kind,
+            });
+        }
+
+        // 按名称排序
+        vars.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(vars)
+    }
+
+    fn get(&self, scope: EnvScope, name: &str) -> Result<Option<EnvVar>, String> {
+        let raw_value = match Self::open_read(scope)?.get_raw_value(name) {
+            Ok(raw_value) => raw_value,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("无法读取{}环境变量 {}: {}", scope.label(), name, e)),
+        };
+        let kind = match raw_value.vtype {
+            REG_SZ => EnvValueKind::String,
+            REG_EXPAND_SZ => EnvValueKind::ExpandString,
+            _ => return Ok(None), // 与 list 一致，忽略非字符串类型的值
+        };
+
+        Ok(Some(EnvVar {
+            name: name.to_string(),
+            value: String::from_reg_value(&raw_value).unwrap_or_default(),
+            kind,
+        }))
+    }
+
+    fn set(&self, scope: EnvScope, var: &EnvVar) -> Result<(), String> {
+        // 按原类型写入，避免 REG_EXPAND_SZ 被改写成 REG_SZ 导致 %VAR% 不再展开
+        let mut raw_value = var.value.to_reg_value();
+        raw_value.vtype = match var.kind {
+            EnvValueKind::String => REG_SZ,
+            EnvValueKind::ExpandString => REG_EXPAND_SZ,
+        };
+
+        Self::open_write(scope)?
+            .set_raw_value(&var.name, &raw_value)
+            .map_err(|e| format!("无法设置{}环境变量: {}", scope.label(), e))
+    }
+
+    fn delete(&self, scope: EnvScope, name: &str) -> Result<(), String> {
+        Self::open_write(scope)?
+            .delete_value(name)
+            .map_err(|e| format!("无法删除{}环境变量: {}", scope.label(), e))
+    }
+
+    fn can_write(&self, scope: EnvScope) -> bool {
+        // 尝试以写权限打开注册表键来检测权限
+        Self::open_write(scope).is_ok()
+    }
+
+    fn notify_changed(&self) {
+        // 通知系统环境变量已更改
+        unsafe {
+            use std::ffi::CString;
+
+            extern "system" {
+                fn SendMessageTimeoutA(
+                    hwnd: *mut std::ffi::c_void,
+                    msg: u32,
+                    wparam: usize,
+                    lparam: isize,
+                    fuflags: u32,
+                    utimeout: u32,
+                    lpdwresult: *mut usize,
+                ) -> isize;
+            }
+
+            const HWND_BROADCAST: *mut std::ffi::c_void = 0xffff as *mut std::ffi::c_void;
+            const WM_SETTINGCHANGE: u32 = 0x001A;
+            const SMTO_ABORTIFHUNG: u32 = 0x0002;
+
+            let env_cstring = CString::new("Environment").unwrap();
+            let mut result: usize = 0;
+
+            SendMessageTimeoutA(
+                HWND_BROADCAST,
+                WM_SETTINGCHANGE,
+                0,
+                env_cstring.as_ptr() as isize,
+                SMTO_ABORTIFHUNG,
+                5000,
+                &mut result,
+            );
+        }
+    }
+}
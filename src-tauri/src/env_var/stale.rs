@@ -0,0 +1,254 @@
+use serde::Serialize;
+use std::path::Path;
+
+use super::expand::{segments, Expander, Segment};
+use super::path_analysis::split_path_list;
+use super::store::{EnvFlavor, EnvScope, EnvSnapshot};
+use super::EnvVar;
+
+// 视为可执行文件的扩展名
+const EXECUTABLE_EXTENSIONS: &[&str] = &["exe", "cmd", "bat", "com", "ps1", "sh", "py", "jar"];
+
+/// 变量值的分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueClass {
+    Path,
+    PathList,
+    Url,
+    Plain,
+}
+
+/// 变量失效的原因
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StaleReason {
+    /// 目录不存在（如卸载后的 JAVA_HOME）
+    MissingDirectory { path: String },
+    /// 可执行文件不存在
+    MissingExecutable { path: String },
+    /// 路径不存在
+    MissingPath { path: String },
+    /// 期望是目录（*_HOME 等）但指向了文件
+    NotDirectory { path: String },
+    /// 路径列表中有不存在的条目
+    MissingListEntries { entries: Vec<String>, total: usize },
+    /// 引用了未定义的变量，路径无法解析
+    UnresolvedReference { names: Vec<String> },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VarClassification {
+    pub name: String,
+    pub scope: EnvScope,
+    pub class: ValueClass,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StaleVar {
+    pub name: String,
+    pub scope: EnvScope,
+    pub class: ValueClass,
+    pub raw: String,
+    pub expanded: String,
+    pub reason: StaleReason,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StaleScanReport {
+    pub scanned: usize,
+    pub classifications: Vec<VarClassification>,
+    pub stale: Vec<StaleVar>,
+}
+
+pub fn scan_stale(snapshot: &EnvSnapshot) -> StaleScanReport {
+    let flavor = snapshot.flavor;
+    let mut expander = Expander::new(snapshot);
+    let mut classifications = Vec::new();
+    let mut stale = Vec::new();
+
+    for scope in [EnvScope::System, EnvScope::User] {
+        for var in snapshot.vars(scope) {
+            let Some(expansion) = expander.expand_var(scope, &var.name) else {
+                continue;
+            };
+            let class = classify_value(&expansion.value, flavor);
+            classifications.push(VarClassification {
+                name: var.name.clone(),
+                scope,
+                class,
+            });
+
+            if !matches!(class, ValueClass::Path | ValueClass::PathList) {
+                continue;
+            }
+
+            let reason = if !expansion.undefined.is_empty() {
+                Some(StaleReason::UnresolvedReference {
+                    names: expansion.undefined.clone(),
+                })
+            } else {
+                check_var(var, &expansion.value, class, flavor)
+            };
+
+            if let Some(reason) = reason {
+                stale.push(StaleVar {
+                    name: var.name.clone(),
+                    scope,
+                    class,
+                    raw: var.value.clone(),
+                    expanded: expansion.value,
+                    reason,
+                });
+            }
+        }
+    }
+
+    StaleScanReport {
+        scanned: classifications.len(),
+        classifications,
+        stale,
+    }
+}
+
+/// 按值的形态分类：URL、单个路径、路径列表或普通字符串
+pub fn classify_value(value: &str, flavor: EnvFlavor) -> ValueClass {
+    let value = value.trim();
+    if value.is_empty() {
+        return ValueClass::Plain;
+    }
+    if is_url(value) {
+        return ValueClass::Url;
+    }
+
+    let parts: Vec<String> = split_path_list(value, flavor)
+        .into_iter()
+        .filter(|part| !part.trim().is_empty())
+        .collect();
+    if parts.len() > 1 {
+        // 大部分条目像路径时才视为路径列表，避免把 PATHEXT 这类值误判
+        let path_like = parts
+            .iter()
+            .filter(|part| looks_like_path(part, flavor))
+            .count();
+        if path_like * 2 > parts.len() {
+            return ValueClass::PathList;
+        }
+        return ValueClass::Plain;
+    }
+
+    if looks_like_path(value, flavor) {
+        ValueClass::Path
+    } else {
+        ValueClass::Plain
+    }
+}
+
+fn is_url(value: &str) -> bool {
+    match value.split_once("://") {
+        Some((scheme, _)) => {
+            !scheme.is_empty()
+                && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+fn looks_like_path(value: &str, flavor: EnvFlavor) -> bool {
+    let value = value.trim().trim_matches('"');
+
+    // 以未能展开的变量开头的路径（如 `%TOOLS%\bin`、`$TOOLS/bin`）
+    if let [Segment::Ref { .. }, Segment::Text(rest), ..] = segments(value, flavor).as_slice() {
+        if rest.starts_with(['/', '\\']) {
+            return true;
+        }
+    }
+
+    match flavor {
+        EnvFlavor::Windows => {
+            let bytes = value.as_bytes();
+            (bytes.len() >= 3
+                && bytes[0].is_ascii_alphabetic()
+                && bytes[1] == b':'
+                && matches!(bytes[2], b'\\' | b'/'))
+                || value.starts_with("\\\\")
+        }
+        EnvFlavor::Posix => value.starts_with('/') || value.starts_with("~/"),
+    }
+}
+
+// 名称暗示值应为目录的变量
+fn expects_directory(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    ["_HOME", "_ROOT", "_DIR"]
+        .iter()
+        .any(|suffix| upper.ends_with(suffix))
+        || matches!(upper.as_str(), "GOROOT" | "GOPATH")
+}
+
+fn resolve_home(path: &str) -> String {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .map(|home| home.join(rest).to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string()),
+        None => path.to_string(),
+    }
+}
+
+fn check_var(
+    var: &EnvVar,
+    expanded: &str,
+    class: ValueClass,
+    flavor: EnvFlavor,
+) -> Option<StaleReason> {
+    match class {
+        ValueClass::PathList => {
+            let entries: Vec<String> = split_path_list(expanded, flavor)
+                .into_iter()
+                .map(|entry| entry.trim().trim_matches('"').to_string())
+                .filter(|entry| !entry.is_empty())
+                .collect();
+            let missing: Vec<String> = entries
+                .iter()
+                .filter(|entry| looks_like_path(entry, flavor))
+                .filter(|entry| !Path::new(&resolve_home(entry)).exists())
+                .cloned()
+                .collect();
+
+            (!missing.is_empty()).then_some(StaleReason::MissingListEntries {
+                entries: missing,
+                total: entries.len(),
+            })
+        }
+        ValueClass::Path => {
+            let path = expanded.trim().trim_matches('"').to_string();
+            let target = resolve_home(&path);
+            let target = Path::new(&target);
+
+            match std::fs::metadata(target) {
+                Ok(metadata) => (expects_directory(&var.name) && !metadata.is_dir())
+                    .then_some(StaleReason::NotDirectory { path }),
+                Err(_) => {
+                    let is_executable = target
+                        .extension()
+                        .and_then(|ext| ext.to_str())
+                        .is_some_and(|ext| {
+                            EXECUTABLE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str())
+                        });
+
+                    Some(if is_executable {
+                        StaleReason::MissingExecutable { path }
+                    } else if expects_directory(&var.name) {
+                        StaleReason::MissingDirectory { path }
+                    } else {
+                        StaleReason::MissingPath { path }
+                    })
+                }
+            }
+        }
+        ValueClass::Url | ValueClass::Plain => None,
+    }
+}
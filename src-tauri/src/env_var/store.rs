@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, LazyLock, RwLock};
+
+use super::file_store::FileStore;
+use super::EnvVar;
+
+// 用于将环境变量管理器指向文件后端的环境变量
+pub const ENV_STORE_FILE_VAR: &str = "MY_TOOLS_ENV_STORE";
+
+// 注册表中保存环境变量的键（分别位于 HKLM 和 HKCU 下）
+pub const SYSTEM_ENV_KEY: &str = "SYSTEM\\CurrentControlSet\\Control\\Session Manager\\Environment";
+pub const USER_ENV_KEY: &str = "Environment";
+
+/// 环境变量作用域
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvScope {
+    System,
+    User,
+}
+
+impl EnvScope {
+    pub fn from_is_system(is_system: bool) -> Self {
+        if is_system {
+            EnvScope::System
+        } else {
+            EnvScope::User
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            EnvScope::System => "系统",
+            EnvScope::User => "用户",
+        }
+    }
+}
+
+/// 变量名与 PATH 的平台规则
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvFlavor {
+    Windows,
+    Posix,
+}
+
+impl EnvFlavor {
+    /// Windows 变量名不区分大小写，POSIX 区分
+    pub fn names_equal(self, a: &str, b: &str) -> bool {
+        match self {
+            EnvFlavor::Windows => a.eq_ignore_ascii_case(b),
+            EnvFlavor::Posix => a == b,
+        }
+    }
+
+    pub fn path_separator(self) -> char {
+        match self {
+            EnvFlavor::Windows => ';',
+            EnvFlavor::Posix => ':',
+        }
+    }
+
+    /// PATH 变量的惯用名称
+    pub fn path_var_name(self) -> &'static str {
+        match self {
+            EnvFlavor::Windows => "Path",
+            EnvFlavor::Posix => "PATH",
+        }
+    }
+}
+
+/// 后端描述信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvStoreInfo {
+    pub kind: String,
+    pub location: String,
+    pub flavor: EnvFlavor,
+}
+
+/// 环境变量存储后端
+///
+/// 所有命令都通过该 trait 读写环境变量，注册表只是其中一种实现。
+pub trait EnvStore: Send + Sync {
+    fn info(&self) -> EnvStoreInfo;
+
+    fn flavor(&self) -> EnvFlavor {
+        EnvFlavor::Windows
+    }
+
+    /// 列出作用域下的全部变量（按名称排序）
+    fn list(&self, scope: EnvScope) -> Result<Vec<EnvVar>, String>;
+
+    /// 读取单个变量，不存在时返回 None
+    fn get(&self, scope: EnvScope, name: &str) -> Result<Option<EnvVar>, String>;
+
+    fn set(&self, scope: EnvScope, var: &EnvVar) -> Result<(), String>;
+
+    fn delete(&self, scope: EnvScope, name: &str) -> Result<(), String>;
+
+    /// 当前进程是否有权限写入该作用域
+    fn can_write(&self, _scope: EnvScope) -> bool {
+        true
+    }
+
+    /// 写入完成后通知系统环境已变化
+    fn notify_changed(&self) {}
+}
+
+/// 某一时刻系统和用户两个作用域的变量
+#[derive(Debug, Clone)]
+pub struct EnvSnapshot {
+    pub flavor: EnvFlavor,
+    pub system_vars: Vec<EnvVar>,
+    pub user_vars: Vec<EnvVar>,
+}
+
+impl EnvSnapshot {
+    pub fn load(store: &dyn EnvStore) -> Result<Self, String> {
+        Ok(EnvSnapshot {
+            flavor: store.flavor(),
+            system_vars: store.list(EnvScope::System)?,
+            user_vars: store.list(EnvScope::User)?,
+        })
+    }
+
+    pub fn vars(&self, scope: EnvScope) -> &[EnvVar] {
+        match scope {
+            EnvScope::System => &self.system_vars,
+            EnvScope::User => &self.user_vars,
+        }
+    }
+
+    pub fn get(&self, scope: EnvScope, name: &str) -> Option<&EnvVar> {
+        self.vars(scope)
+            .iter()
+            .find(|var| self.flavor.names_equal(&var.name, name))
+    }
+}
+
+static ACTIVE_STORE: LazyLock<RwLock<Arc<dyn EnvStore>>> =
+    LazyLock::new(|| RwLock::new(default_store()));
+
+// 默认后端：设置了 MY_TOOLS_ENV_STORE 时使用文件后端，否则使用平台后端
+fn default_store() -> Arc<dyn EnvStore> {
+    if let Ok(path) = std::env::var(ENV_STORE_FILE_VAR) {
+        if !path.trim().is_empty() {
+            return Arc::new(FileStore::new(path));
+        }
+    }
+
+    platform_store()
+}
+
+#[cfg(windows)]
+fn platform_store() -> Arc<dyn EnvStore> {
+    Arc::new(super::registry::RegistryStore)
+}
+
+#[cfg(target_os = "linux")]
+fn platform_store() -> Arc<dyn EnvStore> {
+    Arc::new(super::linux_store::LinuxStore::new(
+        super::linux_store::LinuxEnvPaths::default(),
+    ))
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn platform_store() -> Arc<dyn EnvStore> {
+    let path = dirs::data_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("my-tools")
+        .join("env_store.json");
+    Arc::new(FileStore::new(path))
+}
+
+/// 获取当前使用的后端
+pub fn current_store() -> Arc<dyn EnvStore> {
+    ACTIVE_STORE
+        .read()
+        .map(|store| store.clone())
+        .unwrap_or_else(|poisoned| poisoned.into_inner().clone())
+}
+
+/// 切换后端，传入 None 时恢复默认后端
+pub fn replace_store(store: Option<Arc<dyn EnvStore>>) {
+    let store = store.unwrap_or_else(default_store);
+    match ACTIVE_STORE.write() {
+        Ok(mut active) => *active = store,
+        Err(poisoned) => *poisoned.into_inner() = store,
+    }
+}
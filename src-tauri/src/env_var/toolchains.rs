@@ -0,0 +1,602 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::expand::Expander;
+use super::path_analysis::{normalize_path, split_path_list};
+use super::store::EnvSnapshot;
+
+/// 可识别的工具链
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolKind {
+    Jdk,
+    Python,
+    Node,
+    Go,
+    Rust,
+    Maven,
+    Gradle,
+}
+
+impl ToolKind {
+    /// 约定俗成的安装目录变量，Python 和 Rust 没有
+    pub fn home_var(self) -> Option<&'static str> {
+        match self {
+            ToolKind::Jdk => Some("JAVA_HOME"),
+            ToolKind::Node => Some("NODE_HOME"),
+            ToolKind::Go => Some("GOROOT"),
+            ToolKind::Maven => Some("MAVEN_HOME"),
+            ToolKind::Gradle => Some("GRADLE_HOME"),
+            ToolKind::Python | ToolKind::Rust => None,
+        }
+    }
+
+    // 用于判断目录是否为该工具安装目录的主程序（不含扩展名）
+    fn executable(self) -> &'static str {
+        match self {
+            ToolKind::Jdk => "java",
+            ToolKind::Python => "python",
+            ToolKind::Node => "node",
+            ToolKind::Go => "go",
+            ToolKind::Rust => "rustc",
+            ToolKind::Maven => "mvn",
+            ToolKind::Gradle => "gradle",
+        }
+    }
+}
+
+/// 一个扫描位置：可以是安装目录本身，也可以是包含多个版本的父目录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolLocation {
+    pub kind: ToolKind,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DiscoveryOptions {
+    /// 额外扫描的位置
+    pub locations: Vec<ToolLocation>,
+    /// 是否同时扫描默认位置
+    pub include_defaults: bool,
+}
+
+impl Default for DiscoveryOptions {
+    fn default() -> Self {
+        DiscoveryOptions {
+            locations: Vec::new(),
+            include_defaults: true,
+        }
+    }
+}
+
+/// 版本号的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionSource {
+    /// JDK 的 release 文件、Go 的 VERSION 文件
+    ReleaseFile,
+    /// 头文件、清单、jar 文件名等安装附带的元数据
+    Metadata,
+    DirectoryName,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveredTool {
+    pub kind: ToolKind,
+    pub home: String,
+    pub version: Option<String>,
+    pub version_source: Option<VersionSource>,
+    /// 建议设置的安装目录变量
+    pub home_var: Option<String>,
+    /// 建议加入 PATH 的目录
+    pub path_entries: Vec<String>,
+    /// 安装目录变量当前是否已指向该目录
+    pub is_current_home: bool,
+    /// 建议的 PATH 目录是否都已在当前生效的 PATH 中
+    pub in_path: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveryReport {
+    /// 实际存在并被扫描的位置
+    pub scanned: Vec<ToolLocation>,
+    pub tools: Vec<DiscoveredTool>,
+}
+
+/// 当前系统上各工具链常见的安装位置
+pub fn default_locations() -> Vec<ToolLocation> {
+    let home = dirs::home_dir().unwrap_or_default();
+    let mut locations: Vec<(ToolKind, PathBuf)> = vec![
+        (ToolKind::Jdk, home.join(".jdks")),
+        (ToolKind::Jdk, home.join(".sdkman/candidates/java")),
+        (ToolKind::Python, home.join(".pyenv/versions")),
+        (ToolKind::Node, home.join(".nvm/versions/node")),
+        (ToolKind::Node, home.join(".volta/tools/image/node")),
+        (ToolKind::Go, home.join("sdk")),
+        (ToolKind::Rust, home.join(".rustup/toolchains")),
+        (ToolKind::Maven, home.join(".sdkman/candidates/maven")),
+        (ToolKind::Gradle, home.join(".sdkman/candidates/gradle")),
+    ];
+
+    if cfg!(windows) {
+        let env_dir = |name: &str, fallback: &str| {
+            std::env::var_os(name).map_or_else(|| PathBuf::from(fallback), PathBuf::from)
+        };
+        let program_files = env_dir("ProgramFiles", "C:\\Program Files");
+        let local_app_data = env_dir("LOCALAPPDATA", "");
+        let app_data = env_dir("APPDATA", "");
+
+        locations.extend([
+            (ToolKind::Jdk, program_files.join("Java")),
+            (ToolKind::Jdk, program_files.join("Eclipse Adoptium")),
+            (ToolKind::Jdk, program_files.join("Microsoft")),
+            (ToolKind::Jdk, program_files.join("Zulu")),
+            (ToolKind::Python, local_app_data.join("Programs\\Python")),
+            (ToolKind::Python, home.join(".pyenv\\pyenv-win\\versions")),
+            (ToolKind::Node, program_files.join("nodejs")),
+            (ToolKind::Node, app_data.join("nvm")),
+            (ToolKind::Go, program_files.join("Go")),
+            (ToolKind::Go, PathBuf::from("C:\\Go")),
+            (ToolKind::Maven, program_files.join("Apache\\Maven")),
+            (ToolKind::Maven, PathBuf::from("C:\\tools")),
+            (ToolKind::Gradle, PathBuf::from("C:\\Gradle")),
+            (ToolKind::Gradle, PathBuf::from("C:\\tools")),
+        ]);
+    } else {
+        locations.extend([
+            (ToolKind::Jdk, PathBuf::from("/usr/lib/jvm")),
+            (
+                ToolKind::Jdk,
+                PathBuf::from("/Library/Java/JavaVirtualMachines"),
+            ),
+            (ToolKind::Python, PathBuf::from("/opt/python")),
+            (ToolKind::Node, PathBuf::from("/usr/local/n/versions/node")),
+            (ToolKind::Go, PathBuf::from("/usr/local/go")),
+            (ToolKind::Maven, PathBuf::from("/opt/maven")),
+            (ToolKind::Maven, PathBuf::from("/usr/share/maven")),
+            (ToolKind::Gradle, PathBuf::from("/opt/gradle")),
+        ]);
+    }
+
+    locations
+        .into_iter()
+        .map(|(kind, path)| ToolLocation {
+            kind,
+            path: path.to_string_lossy().to_string(),
+        })
+        .collect()
+}
+
+/// 扫描各位置中的工具链安装，并与当前环境对比给出建议
+pub fn discover(snapshot: &EnvSnapshot, options: &DiscoveryOptions) -> DiscoveryReport {
+    let mut locations = options.locations.clone();
+    if options.include_defaults {
+        locations.extend(default_locations());
+    }
+
+    let flavor = snapshot.flavor;
+    let mut expander = Expander::new(snapshot);
+    let path_entries: HashSet<String> = expander
+        .effective_value(flavor.path_var_name())
+        .map(|value| {
+            split_path_list(&value, flavor)
+                .iter()
+                .map(|entry| normalize_path(entry, flavor))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut scanned = Vec::new();
+    let mut seen: HashSet<(ToolKind, PathBuf)> = HashSet::new();
+    let mut tools = Vec::new();
+
+    for location in locations {
+        let root = PathBuf::from(&location.path);
+        if location.path.trim().is_empty() || !root.is_dir() {
+            continue;
+        }
+
+        for home in find_installs(location.kind, &root) {
+            // 同一安装可能通过符号链接（如 sdkman 的 current）出现多次
+            let key = fs::canonicalize(&home).unwrap_or_else(|_| home.clone());
+            if !seen.insert((location.kind, key)) {
+                continue;
+            }
+
+            let (version, version_source) = match read_version(location.kind, &home) {
+                Some((version, source)) => (Some(version), Some(source)),
+                None => (None, None),
+            };
+            let home_str = home.to_string_lossy().to_string();
+            let entries: Vec<String> = bin_dirs(location.kind, &home)
+                .iter()
+                .map(|dir| dir.to_string_lossy().to_string())
+                .collect();
+            let home_var = location.kind.home_var();
+
+            tools.push(DiscoveredTool {
+                kind: location.kind,
+                is_current_home: home_var
+                    .and_then(|name| expander.effective_value(name))
+                    .is_some_and(|value| {
+                        normalize_path(&value, flavor) == normalize_path(&home_str, flavor)
+                    }),
+                in_path: entries
+                    .iter()
+                    .all(|entry| path_entries.contains(&normalize_path(entry, flavor))),
+                home: home_str,
+                version,
+                version_source,
+                home_var: home_var.map(str::to_string),
+                path_entries: entries,
+            });
+        }
+
+        scanned.push(location);
+    }
+
+    DiscoveryReport { scanned, tools }
+}
+
+// 位置本身是安装目录时只返回它，否则返回其中是安装目录的子目录
+fn find_installs(kind: ToolKind, root: &Path) -> Vec<PathBuf> {
+    if let Some(home) = install_home(kind, root) {
+        return vec![home];
+    }
+
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut children: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    children.sort();
+
+    children
+        .into_iter()
+        .filter_map(|child| install_home(kind, &child))
+        .collect()
+}
+
+// 判断目录是否为安装目录；macOS 的 JDK 安装在 xxx.jdk/Contents/Home 中
+fn install_home(kind: ToolKind, dir: &Path) -> Option<PathBuf> {
+    if kind == ToolKind::Jdk {
+        let mac_home = dir.join("Contents").join("Home");
+        if has_executable(kind, &mac_home) {
+            return Some(mac_home);
+        }
+    }
+    has_executable(kind, dir).then(|| dir.to_path_buf())
+}
+
+fn has_executable(kind: ToolKind, home: &Path) -> bool {
+    let name = kind.executable();
+    bin_dirs(kind, home).iter().any(|dir| {
+        ["", ".exe", ".cmd", ".bat"]
+            .iter()
+            .any(|ext| dir.join(format!("{}{}", name, ext)).is_file())
+            || (kind == ToolKind::Python && dir.join("python3").is_file())
+    })
+}
+
+// 需要加入 PATH 的目录：Windows 的 Python 和 Node 可执行文件直接位于安装目录
+fn bin_dirs(kind: ToolKind, home: &Path) -> Vec<PathBuf> {
+    match kind {
+        ToolKind::Python if home.join("python.exe").is_file() => {
+            vec![home.to_path_buf(), home.join("Scripts")]
+        }
+        ToolKind::Node if home.join("node.exe").is_file() => vec![home.to_path_buf()],
+        _ => vec![home.join("bin")],
+    }
+}
+
+fn read_version(kind: ToolKind, home: &Path) -> Option<(String, VersionSource)> {
+    let from_files = match kind {
+        ToolKind::Jdk => read_key_value(&home.join("release"), "JAVA_VERSION")
+            .map(|version| (version, VersionSource::ReleaseFile)),
+        ToolKind::Go => fs::read_to_string(home.join("VERSION"))
+            .ok()
+            .and_then(|content| {
+                let first = content.lines().next()?.trim();
+                let version = first.strip_prefix("go").unwrap_or(first);
+                (!version.is_empty()).then(|| (version.to_string(), VersionSource::ReleaseFile))
+            }),
+        ToolKind::Python => {
+            python_patchlevel(home).map(|version| (version, VersionSource::Metadata))
+        }
+        ToolKind::Node => node_version_header(&home.join("include/node/node_version.h"))
+            .map(|version| (version, VersionSource::Metadata)),
+        ToolKind::Rust => {
+            rust_manifest_version(home).map(|version| (version, VersionSource::Metadata))
+        }
+        ToolKind::Maven => jar_version(&home.join("lib"), "maven-core-")
+            .map(|version| (version, VersionSource::Metadata)),
+        ToolKind::Gradle => jar_version(&home.join("lib"), "gradle-launcher-")
+            .map(|version| (version, VersionSource::Metadata)),
+    };
+
+    from_files.or_else(|| {
+        let name = home_dir_name(kind, home)?;
+        version_from_dir_name(kind, &name).map(|version| (version, VersionSource::DirectoryName))
+    })
+}
+
+// 读取 KEY="value" 格式文件中的值
+fn read_key_value(path: &Path, key: &str) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    content.lines().find_map(|line| {
+        let (name, value) = line.split_once('=')?;
+        (name.trim() == key).then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+// Python 的 patchlevel.h 中有 #define PY_VERSION "3.11.4"
+fn python_patchlevel(home: &Path) -> Option<String> {
+    let mut candidates = vec![
+        home.join("include").join("patchlevel.h"),
+        home.join("Include").join("patchlevel.h"),
+    ];
+    if let Ok(entries) = fs::read_dir(home.join("include")) {
+        candidates.extend(
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .map(|dir| dir.join("patchlevel.h")),
+        );
+    }
+
+    candidates.iter().find_map(|path| {
+        let content = fs::read_to_string(path).ok()?;
+        content.lines().find_map(|line| {
+            let value = line.trim().strip_prefix("#define")?.trim();
+            let value = value.strip_prefix("PY_VERSION")?.trim();
+            Some(value.trim_matches('"').to_string())
+        })
+    })
+}
+
+// node_version.h 中分别定义了 NODE_MAJOR_VERSION / NODE_MINOR_VERSION / NODE_PATCH_VERSION
+fn node_version_header(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let define = |name: &str| {
+        content.lines().find_map(|line| {
+            let value = line.trim().strip_prefix("#define")?.trim();
+            let value = value.strip_prefix(name)?;
+            value
+                .starts_with(char::is_whitespace)
+                .then(|| value.trim().to_string())
+        })
+    };
+
+    Some(format!(
+        "{}.{}.{}",
+        define("NODE_MAJOR_VERSION")?,
+        define("NODE_MINOR_VERSION")?,
+        define("NODE_PATCH_VERSION")?
+    ))
+}
+
+// rustup 安装的工具链带有渠道清单，其中 [pkg.rustc] 下的 version = "1.77.0 (aedd173a2 2024-03-17)"
+fn rust_manifest_version(home: &Path) -> Option<String> {
+    let content =
+        fs::read_to_string(home.join("lib/rustlib/multirust-channel-manifest.toml")).ok()?;
+    let mut in_rustc = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_rustc = line == "[pkg.rustc]";
+        } else if in_rustc {
+            if let Some(value) = line.strip_prefix("version") {
+                let value = value.trim().strip_prefix('=')?.trim().trim_matches('"');
+                return value.split_whitespace().next().map(str::to_string);
+            }
+        }
+    }
+    None
+}
+
+// 从 lib 目录中形如 maven-core-3.9.6.jar 的文件名读取版本
+fn jar_version(lib: &Path, prefix: &str) -> Option<String> {
+    fs::read_dir(lib).ok()?.find_map(|entry| {
+        let name = entry.ok()?.file_name().to_string_lossy().to_string();
+        let version = name.strip_prefix(prefix)?.strip_suffix(".jar")?;
+        version
+            .starts_with(|c: char| c.is_ascii_digit())
+            .then(|| version.to_string())
+    })
+}
+
+// 用于推断版本的目录名，macOS JDK 取 xxx.jdk 这一层
+fn home_dir_name(kind: ToolKind, home: &Path) -> Option<String> {
+    let dir = if kind == ToolKind::Jdk && home.ends_with("Contents/Home") {
+        home.parent()?.parent()?
+    } else {
+        home
+    };
+    Some(dir.file_name()?.to_string_lossy().to_string())
+}
+
+// 从目录名推断版本，如 jdk-17.0.2+8、v20.11.0、Python311、stable-x86_64-pc-windows-msvc
+fn version_from_dir_name(kind: ToolKind, name: &str) -> Option<String> {
+    if kind == ToolKind::Rust {
+        // 工具链目录名为 <渠道或版本>-<目标平台>
+        let channel = name.split('-').next()?;
+        return (!channel.is_empty()).then(|| channel.to_string());
+    }
+
+    let start = name.find(|c: char| c.is_ascii_digit())?;
+    let version: String = name[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || matches!(c, '.' | '_' | '+'))
+        .collect();
+    let version = version.trim_end_matches(['.', '_', '+']).to_string();
+
+    // Windows 的 Python 安装目录名不带点，如 Python311
+    if kind == ToolKind::Python && !version.contains('.') && version.len() >= 2 {
+        return Some(format!("{}.{}", &version[..1], &version[1..]));
+    }
+    Some(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 在 root 下创建文件（自动创建父目录）
+    fn touch(root: &Path, relative: &str, content: &str) {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn reads_versions_from_release_files_and_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+
+        touch(
+            root,
+            "jdk/release",
+            "IMPLEMENTOR=\"Eclipse Adoptium\"\nJAVA_VERSION=\"17.0.9\"\n",
+        );
+        touch(root, "go/VERSION", "go1.22.1\ntime 2024-03-01T00:00:00Z\n");
+        touch(
+            root,
+            "python/include/python3.11/patchlevel.h",
+            "#define PY_MAJOR_VERSION 3\n#define PY_VERSION \"3.11.4\"\n",
+        );
+        touch(
+            root,
+            "node/include/node/node_version.h",
+            "#define NODE_MAJOR_VERSION 20\n#define NODE_MINOR_VERSION 11\n#define NODE_PATCH_VERSION 0\n#define NODE_VERSION_IS_RELEASE 1\n",
+        );
+        touch(
+            root,
+            "rust/lib/rustlib/multirust-channel-manifest.toml",
+            "[pkg.cargo]\nversion = \"1.76.0 (c84b36747 2024-01-18)\"\n\n[pkg.rustc]\nversion = \"1.77.0 (aedd173a2 2024-03-17)\"\n",
+        );
+        touch(root, "maven/lib/maven-core-3.9.6.jar", "");
+        touch(root, "maven/lib/maven-core-api.jar", "");
+        touch(root, "gradle/lib/gradle-launcher-8.5.jar", "");
+
+        let cases = [
+            (ToolKind::Jdk, "jdk", "17.0.9", VersionSource::ReleaseFile),
+            (ToolKind::Go, "go", "1.22.1", VersionSource::ReleaseFile),
+            (
+                ToolKind::Python,
+                "python",
+                "3.11.4",
+                VersionSource::Metadata,
+            ),
+            (ToolKind::Node, "node", "20.11.0", VersionSource::Metadata),
+            (ToolKind::Rust, "rust", "1.77.0", VersionSource::Metadata),
+            (ToolKind::Maven, "maven", "3.9.6", VersionSource::Metadata),
+            (ToolKind::Gradle, "gradle", "8.5", VersionSource::Metadata),
+        ];
+        for (kind, home, expected, source) in cases {
+            assert_eq!(
+                read_version(kind, &root.join(home)),
+                Some((expected.to_string(), source)),
+                "{:?}",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn falls_back_to_directory_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+
+        let cases = [
+            (ToolKind::Jdk, "jdk-17.0.2+8", Some("17.0.2+8")),
+            (ToolKind::Node, "v20.11.0", Some("20.11.0")),
+            (ToolKind::Python, "Python311", Some("3.11")),
+            (
+                ToolKind::Rust,
+                "stable-x86_64-pc-windows-msvc",
+                Some("stable"),
+            ),
+            (ToolKind::Maven, "apache-maven-3.9.6", Some("3.9.6")),
+            (ToolKind::Go, "go", None),
+        ];
+        for (kind, name, expected) in cases {
+            let home = root.join(name);
+            fs::create_dir_all(&home).unwrap();
+            assert_eq!(
+                read_version(kind, &home),
+                expected.map(|version| (version.to_string(), VersionSource::DirectoryName)),
+                "{}",
+                name
+            );
+        }
+
+        // 元数据文件优先于目录名
+        touch(root, "jdk-17.0.2+8/release", "JAVA_VERSION=\"17.0.2\"\n");
+        assert_eq!(
+            read_version(ToolKind::Jdk, &root.join("jdk-17.0.2+8")),
+            Some(("17.0.2".to_string(), VersionSource::ReleaseFile))
+        );
+
+        // macOS JDK 的版本取 xxx.jdk 这一层的目录名
+        let mac_home = root.join("temurin-21.jdk/Contents/Home");
+        fs::create_dir_all(&mac_home).unwrap();
+        assert_eq!(
+            read_version(ToolKind::Jdk, &mac_home),
+            Some(("21".to_string(), VersionSource::DirectoryName))
+        );
+    }
+
+    #[test]
+    fn detects_install_homes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+
+        touch(root, "jdk17/bin/java", "");
+        touch(root, "zulu-21.jdk/Contents/Home/bin/java", "");
+        touch(root, "Python311/python.exe", "");
+        touch(root, "pyenv-3.12/bin/python3", "");
+        touch(root, "node-win/node.exe", "");
+        touch(root, "maven/bin/mvn.cmd", "");
+        touch(root, "empty/bin/README", "");
+
+        assert_eq!(
+            install_home(ToolKind::Jdk, &root.join("jdk17")),
+            Some(root.join("jdk17"))
+        );
+        assert_eq!(
+            install_home(ToolKind::Jdk, &root.join("zulu-21.jdk")),
+            Some(root.join("zulu-21.jdk/Contents/Home"))
+        );
+        assert_eq!(
+            install_home(ToolKind::Python, &root.join("Python311")),
+            Some(root.join("Python311"))
+        );
+        assert_eq!(
+            install_home(ToolKind::Python, &root.join("pyenv-3.12")),
+            Some(root.join("pyenv-3.12"))
+        );
+        assert_eq!(
+            install_home(ToolKind::Node, &root.join("node-win")),
+            Some(root.join("node-win"))
+        );
+        assert_eq!(
+            install_home(ToolKind::Maven, &root.join("maven")),
+            Some(root.join("maven"))
+        );
+
+        // 没有对应的可执行文件，或可执行文件属于其他工具
+        assert_eq!(install_home(ToolKind::Jdk, &root.join("empty")), None);
+        assert_eq!(install_home(ToolKind::Node, &root.join("jdk17")), None);
+        assert_eq!(install_home(ToolKind::Jdk, root), None);
+
+        // 父目录中包含多个版本时按名称排序返回各安装目录
+        assert_eq!(
+            find_installs(ToolKind::Jdk, root),
+            vec![root.join("jdk17"), root.join("zulu-21.jdk/Contents/Home")]
+        );
+    }
+}
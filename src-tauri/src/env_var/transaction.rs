@@ -0,0 +1,159 @@
+use super::journal::{record_changes, ChangeOrigin};
+use super::store::{EnvScope, EnvSnapshot, EnvStore};
+use super::EnvVar;
+
+/// 对单个变量的修改
+#[derive(Debug, Clone)]
+pub enum EnvChange {
+    Set { scope: EnvScope, var: EnvVar },
+    Delete { scope: EnvScope, name: String },
+}
+
+impl EnvChange {
+    pub fn scope(&self) -> EnvScope {
+        match self {
+            EnvChange::Set { scope, .. } | EnvChange::Delete { scope, .. } => *scope,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            EnvChange::Set { var, .. } => &var.name,
+            EnvChange::Delete { name, .. } => name,
+        }
+    }
+}
+
+/// 事务中某一步写入失败
+#[derive(Debug, Clone)]
+pub struct TransactionError {
+    /// 失败的修改在列表中的位置
+    pub failed_index: usize,
+    pub error: String,
+    /// 回滚时出现的错误，为空表示已完全恢复
+    pub rollback_errors: Vec<String>,
+}
+
+impl TransactionError {
+    /// 面向用户的错误信息：失败的修改，以及是否已恢复之前写入的变量
+    pub fn message(&self, changes: &[EnvChange]) -> String {
+        let failed = match changes.get(self.failed_index) {
+            Some(change) => format!(
+                "写入{}变量 {} 失败: {}",
+                change.scope().label(),
+                change.name(),
+                self.error
+            ),
+            None => self.error.clone(),
+        };
+
+        if self.failed_index == 0 {
+            failed
+        } else if self.rollback_errors.is_empty() {
+            format!("{}，已撤销其他修改", failed)
+        } else {
+            format!(
+                "{}，以下变量未能恢复，请手动检查：\n{}",
+                failed,
+                self.rollback_errors.join("\n")
+            )
+        }
+    }
+}
+
+/// 依次应用修改，任一步失败时按快照把已写入的变量恢复原状；全部成功后记录到修改日志。
+/// 修改已经生效但日志写入失败时返回警告，调用方应把它告知用户（这些修改无法撤销）
+pub fn apply_changes(
+    store: &dyn EnvStore,
+    snapshot: &EnvSnapshot,
+    changes: &[EnvChange],
+    origin: ChangeOrigin,
+) -> Result<Option<String>, TransactionError> {
+    for (index, change) in changes.iter().enumerate() {
+        let result = match change {
+            EnvChange::Set { scope, var } => store.set(*scope, var),
+            EnvChange::Delete { scope, name } => store.delete(*scope, name),
+        };
+
+        if let Err(error) = result {
+            return Err(TransactionError {
+                failed_index: index,
+                error,
+                rollback_errors: rollback(store, snapshot, &changes[..index]),
+            });
+        }
+    }
+
+    // 修改已经生效，日志写入失败不影响结果
+    Ok(record_changes(snapshot, changes, origin)
+        .err()
+        .map(|e| format!("修改已生效，但未能记录到修改日志，无法撤销本次修改: {}", e)))
+}
+
+fn rollback(store: &dyn EnvStore, snapshot: &EnvSnapshot, applied: &[EnvChange]) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    for change in applied.iter().rev() {
+        let scope = change.scope();
+        let name = change.name();
+        let result = match snapshot.get(scope, name) {
+            Some(previous) => store.set(scope, previous),
+            None => store.delete(scope, name),
+        };
+
+        if let Err(e) = result {
+            errors.push(format!("恢复{}变量 {} 失败: {}", scope.label(), name, e));
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env_var::EnvValueKind;
+
+    fn changes() -> Vec<EnvChange> {
+        vec![
+            EnvChange::Set {
+                scope: EnvScope::User,
+                var: EnvVar {
+                    name: "JAVA_HOME".to_string(),
+                    value: "C:\\jdk".to_string(),
+                    kind: EnvValueKind::String,
+                },
+            },
+            EnvChange::Delete {
+                scope: EnvScope::System,
+                name: "OLD".to_string(),
+            },
+        ]
+    }
+
+    fn error(failed_index: usize, rollback_errors: &[&str]) -> TransactionError {
+        TransactionError {
+            failed_index,
+            error: "拒绝访问".to_string(),
+            rollback_errors: rollback_errors.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn message_names_failed_change_and_rollback_result() {
+        let changes = changes();
+
+        assert_eq!(
+            error(0, &[]).message(&changes),
+            "写入用户变量 JAVA_HOME 失败: 拒绝访问"
+        );
+        assert_eq!(
+            error(1, &[]).message(&changes),
+            "写入系统变量 OLD 失败: 拒绝访问，已撤销其他修改"
+        );
+
+        let message = error(1, &["恢复用户变量 JAVA_HOME 失败: 拒绝访问"]).message(&changes);
+        assert!(!message.contains("已撤销"));
+        assert!(message.ends_with("未能恢复，请手动检查：\n恢复用户变量 JAVA_HOME 失败: 拒绝访问"));
+    }
+}
@@ -0,0 +1,571 @@
+use chrono::Local;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::expand::Expander;
+use super::file_store::write_atomic;
+use super::path_analysis::split_path_list;
+use super::store::{EnvFlavor, EnvScope, EnvSnapshot};
+
+/// 设置后改用该文件保存自定义校验规则（主要用于测试和便携部署）
+pub const RULES_FILE_VAR: &str = "MY_TOOLS_ENV_RULES";
+
+/// Windows 单个环境变量值的最大长度
+const WINDOWS_VALUE_LIMIT: usize = 32767;
+/// 部分旧程序（如 setx、某些安装程序）只能正确处理这个长度以内的 PATH
+const LEGACY_PATH_LIMIT: usize = 2047;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// 规则对变量值所做的检查，除 MaxLength 外都检查展开后的值
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleCheck {
+    /// 值必须匹配正则表达式，negate 为 true 时必须不匹配
+    Regex {
+        pattern: String,
+        #[serde(default)]
+        negate: bool,
+    },
+    /// 按路径分隔符拆分后每一项都必须匹配正则表达式
+    ListItemsRegex { pattern: String },
+    /// 值必须是已存在的目录
+    Directory,
+    /// 值必须是已存在的文件
+    File,
+    /// 值作为目录时，其中必须存在任意一个相对路径
+    ContainsAny { paths: Vec<String> },
+    /// 保存的原始值长度不能超过 max 个字符，Windows 下按 UTF-16 单元计算
+    MaxLength { max: usize },
+}
+
+/// 一条校验规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationRule {
+    pub id: String,
+    /// 变量名，支持 `*` 通配符，如 `*_HOME`
+    pub variable: String,
+    /// 只检查指定作用域，为空时检查两个作用域
+    #[serde(default)]
+    pub scope: Option<EnvScope>,
+    /// 只在指定平台上生效，为空时所有平台都生效
+    #[serde(default)]
+    pub flavor: Option<EnvFlavor>,
+    pub check: RuleCheck,
+    pub severity: Severity,
+    /// 违反规则时显示的说明，为空时根据检查类型生成
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// 用户保存的规则设置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleSettings {
+    pub rules: Vec<ValidationRule>,
+    /// 被禁用的内置规则 id
+    pub disabled_builtin: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleViolation {
+    pub rule_id: String,
+    pub severity: Severity,
+    pub scope: EnvScope,
+    pub name: String,
+    /// 展开后的值
+    pub value: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InvalidRule {
+    pub rule_id: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub generated_at: String,
+    pub flavor: EnvFlavor,
+    pub checked_vars: usize,
+    pub rules_applied: usize,
+    /// 按严重程度从高到低排列
+    pub violations: Vec<RuleViolation>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub infos: usize,
+    /// 无法使用的自定义规则（如正则表达式有误）
+    pub invalid_rules: Vec<InvalidRule>,
+}
+
+fn rule(
+    id: &str,
+    variable: &str,
+    flavor: Option<EnvFlavor>,
+    check: RuleCheck,
+    severity: Severity,
+    message: &str,
+) -> ValidationRule {
+    ValidationRule {
+        id: id.to_string(),
+        variable: variable.to_string(),
+        scope: None,
+        flavor,
+        check,
+        severity,
+        message: Some(message.to_string()),
+    }
+}
+
+/// 内置规则
+pub fn builtin_rules() -> Vec<ValidationRule> {
+    vec![
+        rule(
+            "builtin.java_home",
+            "JAVA_HOME",
+            None,
+            RuleCheck::ContainsAny {
+                paths: vec!["bin/java".to_string(), "bin/java.exe".to_string()],
+            },
+            Severity::Error,
+            "JAVA_HOME 应指向 JDK 安装目录，其中应包含 bin/java",
+        ),
+        rule(
+            "builtin.goroot",
+            "GOROOT",
+            None,
+            RuleCheck::ContainsAny {
+                paths: vec!["bin/go".to_string(), "bin/go.exe".to_string()],
+            },
+            Severity::Error,
+            "GOROOT 应指向 Go 安装目录，其中应包含 bin/go",
+        ),
+        rule(
+            "builtin.gopath",
+            "GOPATH",
+            None,
+            RuleCheck::Directory,
+            Severity::Warning,
+            "GOPATH 应为已存在的目录",
+        ),
+        rule(
+            "builtin.pathext",
+            "PATHEXT",
+            Some(EnvFlavor::Windows),
+            RuleCheck::ListItemsRegex {
+                pattern: r"^\.[A-Za-z0-9]+$".to_string(),
+            },
+            Severity::Error,
+            "PATHEXT 中的每一项都应以 . 开头，如 .EXE",
+        ),
+        rule(
+            "builtin.value_length",
+            "*",
+            Some(EnvFlavor::Windows),
+            RuleCheck::MaxLength {
+                max: WINDOWS_VALUE_LIMIT,
+            },
+            Severity::Error,
+            "变量值超过 Windows 32767 个字符的上限",
+        ),
+        rule(
+            "builtin.path_length",
+            "PATH",
+            Some(EnvFlavor::Windows),
+            RuleCheck::MaxLength {
+                max: LEGACY_PATH_LIMIT,
+            },
+            Severity::Warning,
+            "PATH 超过 2047 个字符，部分旧程序可能会截断或无法读取",
+        ),
+    ]
+}
+
+fn rules_path() -> PathBuf {
+    match std::env::var(RULES_FILE_VAR) {
+        Ok(path) if !path.trim().is_empty() => PathBuf::from(path),
+        _ => dirs::data_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("my-tools")
+            .join("env_rules.json"),
+    }
+}
+
+pub fn load_settings() -> Result<RuleSettings, String> {
+    let path = rules_path();
+    if !path.exists() {
+        return Ok(RuleSettings::default());
+    }
+
+    let content = fs::read_to_string(&path).map_err(|e| format!("读取校验规则失败: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("解析校验规则失败: {}", e))
+}
+
+pub fn save_settings(settings: &RuleSettings) -> Result<(), String> {
+    for rule in &settings.rules {
+        if rule.id.trim().is_empty() {
+            return Err("规则 id 不能为空".to_string());
+        }
+        if rule.id.starts_with("builtin.") {
+            return Err(format!("自定义规则的 id 不能以 builtin. 开头: {}", rule.id));
+        }
+        // 只检查格式，变量名大小写规则不影响能否编译
+        compile(rule, rule.flavor.unwrap_or(EnvFlavor::Windows))?;
+    }
+
+    let path = rules_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建文件夹失败: {}", e))?;
+    }
+    let content =
+        serde_json::to_string_pretty(settings).map_err(|e| format!("序列化数据失败: {}", e))?;
+    write_atomic(&path, &content)
+}
+
+// 规则编译后的形式：变量名匹配器和检查中用到的正则表达式
+struct CompiledRule<'a> {
+    rule: &'a ValidationRule,
+    variable: Regex,
+    pattern: Option<Regex>,
+}
+
+fn compile(rule: &ValidationRule, flavor: EnvFlavor) -> Result<CompiledRule<'_>, String> {
+    // Windows 下变量名不区分大小写，POSIX 下区分（包括含通配符的规则）
+    let variable = format!(
+        "{}^{}$",
+        if flavor == EnvFlavor::Windows {
+            "(?i)"
+        } else {
+            ""
+        },
+        rule.variable
+            .split('*')
+            .map(regex::escape)
+            .collect::<Vec<_>>()
+            .join(".*")
+    );
+    let variable = Regex::new(&variable).map_err(|e| format!("变量名格式错误: {}", e))?;
+
+    let pattern = match &rule.check {
+        RuleCheck::Regex { pattern, .. } | RuleCheck::ListItemsRegex { pattern } => {
+            Some(Regex::new(pattern).map_err(|e| format!("正则表达式错误: {}", e))?)
+        }
+        _ => None,
+    };
+
+    Ok(CompiledRule {
+        rule,
+        variable,
+        pattern,
+    })
+}
+
+impl CompiledRule<'_> {
+    // 检查通过时返回 None，否则返回默认的说明。raw 为保存的原始值，value 为展开后的值
+    fn check(&self, flavor: EnvFlavor, raw: &str, value: &str) -> Option<String> {
+        match &self.rule.check {
+            RuleCheck::Regex { negate, .. } => {
+                let pattern = self.pattern.as_ref()?;
+                (pattern.is_match(value) == *negate).then(|| {
+                    if *negate {
+                        format!("值不应匹配 {}", pattern.as_str())
+                    } else {
+                        format!("值应匹配 {}", pattern.as_str())
+                    }
+                })
+            }
+            RuleCheck::ListItemsRegex { .. } => {
+                let pattern = self.pattern.as_ref()?;
+                let invalid: Vec<String> = split_path_list(value, flavor)
+                    .into_iter()
+                    .filter(|item| !pattern.is_match(item))
+                    .collect();
+                (!invalid.is_empty()).then(|| format!("以下条目格式不正确: {}", invalid.join(", ")))
+            }
+            RuleCheck::Directory => {
+                (!Path::new(value.trim()).is_dir()).then(|| "目录不存在".to_string())
+            }
+            RuleCheck::File => {
+                (!Path::new(value.trim()).is_file()).then(|| "文件不存在".to_string())
+            }
+            RuleCheck::ContainsAny { paths } => {
+                let dir = Path::new(value.trim());
+                (!paths.iter().any(|path| dir.join(path).exists()))
+                    .then(|| format!("目录中未找到 {}", paths.join(" 或 ")))
+            }
+            RuleCheck::MaxLength { max } => {
+                // 系统限制的是保存的值，Windows 按 UTF-16 单元计算长度
+                let length = match flavor {
+                    EnvFlavor::Windows => raw.encode_utf16().count(),
+                    EnvFlavor::Posix => raw.chars().count(),
+                };
+                (length > *max).then(|| format!("长度为 {} 个字符，超过上限 {}", length, max))
+            }
+        }
+    }
+}
+
+/// 按内置规则和自定义规则检查所有变量，生成健康报告
+pub fn check_health(snapshot: &EnvSnapshot, settings: &RuleSettings) -> HealthReport {
+    let flavor = snapshot.flavor;
+    let mut invalid_rules = Vec::new();
+    let mut compiled = Vec::new();
+
+    let builtin = builtin_rules();
+    let rules = builtin
+        .iter()
+        .filter(|rule| !settings.disabled_builtin.contains(&rule.id))
+        .chain(&settings.rules)
+        .filter(|rule| rule.flavor.is_none_or(|rule_flavor| rule_flavor == flavor));
+    for rule in rules {
+        match compile(rule, flavor) {
+            Ok(rule) => compiled.push(rule),
+            Err(error) => invalid_rules.push(InvalidRule {
+                rule_id: rule.id.clone(),
+                error,
+            }),
+        }
+    }
+
+    let mut expander = Expander::new(snapshot);
+    let mut violations = Vec::new();
+    let mut checked_vars = 0;
+
+    for scope in [EnvScope::System, EnvScope::User] {
+        for var in snapshot.vars(scope) {
+            checked_vars += 1;
+            let value = expander
+                .expand_var(scope, &var.name)
+                .map_or_else(|| var.value.clone(), |expansion| expansion.value);
+
+            for rule in &compiled {
+                if rule
+                    .rule
+                    .scope
+                    .is_some_and(|rule_scope| rule_scope != scope)
+                    || !rule.variable.is_match(&var.name)
+                {
+                    continue;
+                }
+
+                if let Some(detail) = rule.check(flavor, &var.value, &value) {
+                    violations.push(RuleViolation {
+                        rule_id: rule.rule.id.clone(),
+                        severity: rule.rule.severity,
+                        scope,
+                        name: var.name.clone(),
+                        value: value.clone(),
+                        message: match &rule.rule.message {
+                            Some(message) => format!("{}（{}）", message, detail),
+                            None => detail,
+                        },
+                    });
+                }
+            }
+        }
+    }
+
+    violations.sort_by_key(|violation| std::cmp::Reverse(violation.severity));
+    let count = |severity: Severity| {
+        violations
+            .iter()
+            .filter(|violation| violation.severity == severity)
+            .count()
+    };
+
+    HealthReport {
+        generated_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        flavor,
+        checked_vars,
+        rules_applied: compiled.len(),
+        errors: count(Severity::Error),
+        warnings: count(Severity::Warning),
+        infos: count(Severity::Info),
+        violations,
+        invalid_rules,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env_var::{EnvValueKind, EnvVar};
+    use std::fs;
+
+    fn var(name: &str, value: &str) -> EnvVar {
+        EnvVar {
+            name: name.to_string(),
+            value: value.to_string(),
+            kind: EnvValueKind::infer(value),
+        }
+    }
+
+    fn snapshot(
+        flavor: EnvFlavor,
+        system_vars: Vec<EnvVar>,
+        user_vars: Vec<EnvVar>,
+    ) -> EnvSnapshot {
+        EnvSnapshot {
+            flavor,
+            system_vars,
+            user_vars,
+        }
+    }
+
+    fn custom(id: &str, variable: &str, pattern: &str) -> ValidationRule {
+        ValidationRule {
+            id: id.to_string(),
+            variable: variable.to_string(),
+            scope: None,
+            flavor: None,
+            check: RuleCheck::Regex {
+                pattern: pattern.to_string(),
+                negate: false,
+            },
+            severity: Severity::Info,
+            message: None,
+        }
+    }
+
+    fn flagged<'a>(report: &'a HealthReport, rule_id: &str) -> Vec<(EnvScope, &'a str)> {
+        report
+            .violations
+            .iter()
+            .filter(|violation| violation.rule_id == rule_id)
+            .map(|violation| (violation.scope, violation.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn builtin_rules_check_install_directories_and_pathext() {
+        let dir = tempfile::tempdir().unwrap();
+        let jdk = dir.path().join("jdk");
+        fs::create_dir_all(jdk.join("bin")).unwrap();
+        fs::write(jdk.join("bin").join("java"), "").unwrap();
+        let go = dir.path().join("go");
+        fs::create_dir(&go).unwrap();
+
+        let snapshot = snapshot(
+            EnvFlavor::Windows,
+            vec![var("PATHEXT", ".COM;.EXE;BAT")],
+            vec![
+                var("JAVA_HOME", &jdk.to_string_lossy()),
+                var("GOROOT", &go.to_string_lossy()),
+                var("GOPATH", &dir.path().join("gopath").to_string_lossy()),
+            ],
+        );
+        let report = check_health(&snapshot, &RuleSettings::default());
+
+        assert!(flagged(&report, "builtin.java_home").is_empty());
+        assert_eq!(
+            flagged(&report, "builtin.goroot"),
+            [(EnvScope::User, "GOROOT")]
+        );
+        assert_eq!(
+            flagged(&report, "builtin.gopath"),
+            [(EnvScope::User, "GOPATH")]
+        );
+        let pathext = &report.violations[0];
+        assert_eq!(pathext.rule_id, "builtin.pathext");
+        assert!(pathext.message.ends_with("（以下条目格式不正确: BAT）"));
+        assert_eq!((report.errors, report.warnings), (2, 1));
+
+        // 禁用的内置规则和只适用于 Windows 的规则不参与检查
+        let settings = RuleSettings {
+            disabled_builtin: vec!["builtin.goroot".to_string()],
+            ..RuleSettings::default()
+        };
+        let posix = EnvSnapshot {
+            flavor: EnvFlavor::Posix,
+            ..snapshot
+        };
+        let report = check_health(&posix, &settings);
+        assert!(flagged(&report, "builtin.goroot").is_empty());
+        assert!(flagged(&report, "builtin.pathext").is_empty());
+        assert_eq!(report.rules_applied, 2);
+    }
+
+    #[test]
+    fn length_rules_check_stored_value_in_utf16_units() {
+        // 系统 PATH 只保存了一个引用，展开后才超长；用户 PATH 只有 1024 个字符，但占 2048 个 UTF-16 单元
+        let snapshot = snapshot(
+            EnvFlavor::Windows,
+            vec![var("LONG", &"x".repeat(3000)), var("Path", "%LONG%")],
+            vec![var("Path", &"😀".repeat(1024))],
+        );
+        let report = check_health(&snapshot, &RuleSettings::default());
+
+        let violations: Vec<&RuleViolation> = report
+            .violations
+            .iter()
+            .filter(|violation| violation.rule_id == "builtin.path_length")
+            .collect();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].scope, EnvScope::User);
+        assert!(violations[0].message.contains("长度为 2048 个字符"));
+        assert!(flagged(&report, "builtin.value_length").is_empty());
+    }
+
+    #[test]
+    fn wildcard_names_follow_platform_case_rules() {
+        let settings = RuleSettings {
+            rules: vec![custom("absolute_home", "*_HOME", "^/")],
+            ..RuleSettings::default()
+        };
+        let vars = vec![
+            var("JAVA_HOME", "jdk"),
+            var("maven_home", "maven"),
+            var("HOME", "/home/me"),
+            var("GRADLE_HOME", "/opt/gradle"),
+        ];
+
+        let report = check_health(
+            &snapshot(EnvFlavor::Posix, Vec::new(), vars.clone()),
+            &settings,
+        );
+        assert_eq!(
+            flagged(&report, "absolute_home"),
+            [(EnvScope::User, "JAVA_HOME")]
+        );
+
+        let report = check_health(&snapshot(EnvFlavor::Windows, Vec::new(), vars), &settings);
+        assert_eq!(
+            flagged(&report, "absolute_home"),
+            [
+                (EnvScope::User, "JAVA_HOME"),
+                (EnvScope::User, "maven_home")
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_custom_rules() {
+        let settings = RuleSettings {
+            rules: vec![
+                custom("broken", "JAVA_HOME", "(unclosed"),
+                custom("valid", "JAVA_HOME", "jdk"),
+            ],
+            ..RuleSettings::default()
+        };
+        let snapshot = snapshot(
+            EnvFlavor::Posix,
+            Vec::new(),
+            vec![var("JAVA_HOME", "/opt/java")],
+        );
+        let report = check_health(&snapshot, &settings);
+
+        assert_eq!(report.invalid_rules.len(), 1);
+        assert_eq!(report.invalid_rules[0].rule_id, "broken");
+        assert!(report.invalid_rules[0].error.starts_with("正则表达式错误"));
+        assert_eq!(flagged(&report, "valid"), [(EnvScope::User, "JAVA_HOME")]);
+    }
+}
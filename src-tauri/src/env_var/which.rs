@@ -0,0 +1,174 @@
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use super::expand::Expander;
+use super::path_analysis::{normalize_path, split_path_list};
+use super::store::{EnvFlavor, EnvScope, EnvSnapshot};
+
+/// 未设置 PATHEXT 时 Windows 使用的默认值
+const DEFAULT_PATHEXT: &str = ".COM;.EXE;.BAT;.CMD;.VBS;.VBE;.JS;.JSE;.WSF;.WSH;.MSC";
+
+/// 命令在某个 PATH 目录中的一个匹配
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandMatch {
+    pub path: String,
+    /// 所在目录在生效 PATH 中的位置
+    pub path_index: usize,
+    /// 目录来自哪个作用域的 PATH
+    pub scope: EnvScope,
+    /// 是否为实际会被执行的那个
+    pub winner: bool,
+    /// Windows 应用商店的应用执行别名（如未安装 Python 时的 python.exe 占位程序）
+    pub store_alias: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WhichReport {
+    pub command: String,
+    pub flavor: EnvFlavor,
+    /// 依次尝试的文件名
+    pub candidates: Vec<String>,
+    /// 搜索过的目录数量（重复目录只算一次）
+    pub searched_dirs: usize,
+    /// 按优先级排列，第一个为生效的匹配，其余被它遮蔽
+    pub matches: Vec<CommandMatch>,
+}
+
+/// 按生效的 PATH（Windows 为系统 PATH 在前、用户 PATH 在后）和 PATHEXT 解析命令
+pub fn resolve(snapshot: &EnvSnapshot, command: &str) -> Result<WhichReport, String> {
+    let command = command.trim();
+    if command.is_empty() {
+        return Err("命令名称不能为空".to_string());
+    }
+    if command.contains(['/', '\\']) {
+        return Err("请输入命令名称而不是路径".to_string());
+    }
+
+    let flavor = snapshot.flavor;
+    let mut expander = Expander::new(snapshot);
+    let candidates = candidate_names(flavor, command, &mut expander);
+
+    // 与 Expander::effective_value 的合并顺序一致，但保留每个目录的作用域
+    let path_name = flavor.path_var_name();
+    let scopes: Vec<EnvScope> = match flavor {
+        EnvFlavor::Windows => vec![EnvScope::System, EnvScope::User],
+        EnvFlavor::Posix if snapshot.get(EnvScope::User, path_name).is_some() => {
+            vec![EnvScope::User]
+        }
+        EnvFlavor::Posix => vec![EnvScope::System],
+    };
+
+    let mut seen: Vec<String> = Vec::new();
+    let mut matches = Vec::new();
+    let mut path_index = 0;
+
+    for scope in scopes {
+        let Some(value) = expander
+            .expand_var(scope, path_name)
+            .map(|expansion| expansion.value)
+        else {
+            continue;
+        };
+
+        for dir in split_path_list(&value, flavor) {
+            let index = path_index;
+            path_index += 1;
+
+            let dir = dir.trim().trim_matches('"').to_string();
+            let normalized = normalize_path(&dir, flavor);
+            if dir.is_empty() || seen.contains(&normalized) {
+                continue;
+            }
+            seen.push(normalized);
+
+            for name in &candidates {
+                let path = Path::new(&dir).join(name);
+                if !is_executable(flavor, &path) {
+                    continue;
+                }
+                matches.push(CommandMatch {
+                    path: path.to_string_lossy().to_string(),
+                    path_index: index,
+                    scope,
+                    winner: matches.is_empty(),
+                    store_alias: is_store_alias(&dir),
+                });
+                // 同一目录中按 PATHEXT 顺序只会找到第一个
+                break;
+            }
+        }
+    }
+
+    Ok(WhichReport {
+        command: command.to_string(),
+        flavor,
+        candidates,
+        searched_dirs: seen.len(),
+        matches,
+    })
+}
+
+// Windows 下命令没有 PATHEXT 中的扩展名时依次补上各扩展名，POSIX 下只查找命令本身
+fn candidate_names(flavor: EnvFlavor, command: &str, expander: &mut Expander) -> Vec<String> {
+    if flavor == EnvFlavor::Posix {
+        return vec![command.to_string()];
+    }
+
+    let pathext = expander
+        .effective_value("PATHEXT")
+        .filter(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_PATHEXT.to_string());
+    let extensions: Vec<String> = pathext
+        .split(';')
+        .map(|ext| ext.trim().to_string())
+        .filter(|ext| !ext.is_empty())
+        .collect();
+
+    let lower = command.to_ascii_lowercase();
+    if extensions
+        .iter()
+        .any(|ext| lower.ends_with(&ext.to_ascii_lowercase()))
+    {
+        return vec![command.to_string()];
+    }
+
+    extensions
+        .iter()
+        .map(|ext| format!("{}{}", command, ext.to_ascii_lowercase()))
+        .collect()
+}
+
+// 应用执行别名是大小为 0 的重解析点，is_file 可能返回 false，因此只要求能读取到元数据
+fn is_executable(flavor: EnvFlavor, path: &Path) -> bool {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return false;
+    };
+    if metadata.is_dir() {
+        return false;
+    }
+    if flavor == EnvFlavor::Windows {
+        return true;
+    }
+
+    // 符号链接以其目标为准
+    let Ok(metadata) = fs::metadata(path) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+fn is_store_alias(dir: &str) -> bool {
+    dir.replace('/', "\\")
+        .to_ascii_lowercase()
+        .trim_end_matches('\\')
+        .ends_with("\\microsoft\\windowsapps")
+}
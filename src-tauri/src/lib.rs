@@ -937,16 +937,55 @@ pub fn run() {
             env_var::get_env_vars,
             env_var::set_env_var,
             env_var::delete_env_var,
+            env_var::get_env_dependency_graph,
+            env_var::analyze_env_var_impact,
             env_var::check_admin_privileges,
             env_var::request_admin_privileges,
             env_var::export_env_vars,
             env_var::export_env_vars_to_path,
+            env_var::export_env_vars_as,
             env_var::get_documents_dir,
             env_var::import_env_vars,
+            env_var::preview_import_env_vars,
+            env_var::diff_env_backups,
+            env_var::get_snapshot_folder,
+            env_var::get_snapshot_policy,
+            env_var::set_snapshot_policy,
+            env_var::list_env_profiles,
+            env_var::save_env_profile,
+            env_var::delete_env_profile,
+            env_var::activate_env_profile,
+            env_var::deactivate_env_profile,
+            env_var::get_active_env_profile,
+            env_var::list_env_journal,
+            env_var::undo_env_change,
+            env_var::undo_env_changes_since,
             env_var::reveal_in_explorer,
             env_var::open_folder,
             env_var::check_paths_exist,
+            env_var::expand_env_vars,
+            env_var::analyze_path,
+            env_var::cleanup_path,
+            env_var::edit_path_list,
+            env_var::find_stale_env_vars,
+            env_var::find_secret_env_vars,
+            env_var::resolve_command,
+            env_var::discover_toolchains,
+            env_var::get_default_tool_locations,
+            env_var::get_builtin_validation_rules,
+            env_var::get_validation_settings,
+            env_var::set_validation_settings,
+            env_var::check_env_health,
+            env_var::export_env_health_report,
+            env_var::check_env_baseline,
+            env_var::fix_env_baseline,
             env_var::scan_config_files,
+            env_var::set_backup_annotation,
+            env_var::prune_backups,
+            env_var::validate_backups,
+            env_var::get_env_store_info,
+            env_var::set_env_store_file,
+            env_var::set_env_store_linux,
             open_file_location,
             search_everything,
             shell_open,
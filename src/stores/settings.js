@@ -4,6 +4,7 @@ export const useSettingsStore = defineStore('settings', {
   state: () => ({
     exportPath: '',
     autoOpenFolder: true,
+    encryptBackups: false, // 导出环境变量备份时是否使用密码加密
     afterOpenProjectBehavior: 'minimize', // 打开项目后的行为：'minimize'（最小化到托盘）、'quit'（退出应用）、'none'（无动作，默认）
     afterCopyCommitBehavior: 'minimize', // 复制提交信息后的行为：'minimize'（最小化到托盘）、'quit'（退出应用）、'none'（无动作，默认）
     vscodeStoragePath: '', // 自定义 VSCode storage.json 路径，留空则自动推断
@@ -381,6 +382,7 @@ export const useSettingsStore = defineStore('settings', {
     resetToDefaults() {
       this.exportPath = ''
       this.autoOpenFolder = true
+      this.encryptBackups = false
       this.afterOpenProjectBehavior = 'minimize'
       this.vscodeStoragePath = ''
       this.traeStoragePath = ''
@@ -0,0 +1,44 @@
+import { ElMessageBox } from 'element-plus'
+
+const scopeLabels = {
+    system: '系统',
+    user: '用户'
+}
+
+// 将 import_env_vars 返回的导入报告整理为提示信息
+export function formatImportReport(report) {
+    if (report.rolled_back) {
+        const failed = report.vars.find((item) => item.status === 'failed')
+        let message = '导入失败，已撤销本次所有修改'
+        if (failed) {
+            message += `：${scopeLabels[failed.scope]}变量 ${failed.name}: ${failed.error}`
+        }
+        if (report.rollback_errors.length > 0) {
+            message += `\n部分变量未能恢复：\n${report.rollback_errors.join('\n')}`
+        }
+        return { type: 'error', message }
+    }
+
+    let message = `导入完成！成功导入 ${report.applied} 个变量`
+    if (report.skipped > 0) {
+        message += `，跳过 ${report.skipped} 个变量`
+    }
+    if (report.warning) {
+        return { type: 'warning', message: `${message}\n${report.warning}` }
+    }
+    return { type: 'success', message }
+}
+
+// 后端读取加密备份但未提供密码时返回的错误
+export const PASSPHRASE_REQUIRED = '该备份已加密，请输入密码'
+
+// 输入加密备份的密码，取消时抛出 'cancel'
+export async function promptPassphrase(title = '输入备份密码') {
+    const { value } = await ElMessageBox.prompt(PASSPHRASE_REQUIRED, title, {
+        inputType: 'password',
+        inputValidator: (value) => !!value || '密码不能为空',
+        confirmButtonText: '确定',
+        cancelButtonText: '取消'
+    })
+    return value
+}